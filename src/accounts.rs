@@ -0,0 +1,32 @@
+use crate::credentials::{self, Credentials};
+use crate::feeds::Feed;
+use crate::moderation::Moderation;
+use crate::user_config::{self, UserConfig};
+
+/// What is kept of an account while another one is used, so switching back
+/// finds it as it was left. Nothing in it is shared with other accounts.
+pub struct Session {
+	pub credentials: Credentials,
+	pub feeds: Vec<Feed>,
+	pub feed: usize,
+	pub username: Option<String>,
+	pub modhash: String,
+	pub moderation: Option<Moderation>,
+}
+
+/// Names of the accounts in the config and the credentials file, and of
+/// `open`, the ones used since rusddit started. The default account is first.
+pub fn names<'a>(config: &UserConfig, open: impl IntoIterator<Item = &'a String>) -> Vec<String> {
+	let mut names: Vec<String> = config
+		.accounts
+		.keys()
+		.cloned()
+		.chain(credentials::accounts())
+		.chain(open.into_iter().cloned())
+		.filter(|x| !x.is_empty())
+		.collect();
+	names.sort();
+	names.dedup();
+	names.insert(0, user_config::DEFAULT_ACCOUNT.to_string());
+	names
+}
@@ -1,9 +1,38 @@
-use crossterm::event::{self, Event, KeyCode};
+use crossterm::{
+	event::{
+		self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, MouseButton, MouseEvent,
+		MouseEventKind,
+	},
+	execute,
+};
+use std::collections::HashMap;
 use std::io;
-use std::path::Path;
 use tui::{backend::Backend, widgets::ListState, Terminal};
 
+use crate::accounts::{self, Session};
+use crate::clipboard;
+use crate::command_line::{self, CommandLine, SORTS, TIMES};
+use crate::credentials::{Credentials, Secret};
+use crate::dialog::{Action, Dialog};
+use crate::feeds::{self, Feed};
+use crate::find::Find;
+use crate::help::Help;
+use crate::highlight::Highlighter;
+use crate::history::{History, View};
+use crate::keymap::{self, Command, Context, KeyState, Keymap, Resolved};
+use crate::layout::PaneSizes;
+use crate::links::{self, LinkPicker, Target};
+use crate::markdown::decode_entities;
+use crate::media::Images;
+use crate::moderation::Moderation;
+use crate::mouse::{self, Clicks, Pane, Regions};
+use crate::opener::{self, Opener};
+use crate::scroll::Extent;
+use crate::status::Status;
+use crate::theme::{ColorSupport, Theme};
 use crate::ui;
+use crate::user_config::{self, Filters, Sorts, UserConfig};
+use crate::viewer::Viewer;
 
 use crate::utils;
 
@@ -13,13 +42,13 @@ pub struct StatefulList<T> {
 }
 
 impl<T> StatefulList<T> {
-	fn with_items(items: Vec<T>) -> StatefulList<T> {
+	pub fn with_items(items: Vec<T>) -> StatefulList<T> {
 		let mut sl = StatefulList {
 			state: ListState::default(),
 			items,
 		};
 		sl.state.select(Some(0));
-		return sl;
+		sl
 	}
 
 	pub fn next(&mut self) {
@@ -36,11 +65,20 @@ impl<T> StatefulList<T> {
 		self.state.select(Some(i));
 	}
 
+	/// Like `next` but never selects the slot past the last item.
+	pub fn cycle_next(&mut self) {
+		let i = match self.state.selected() {
+			Some(i) if i + 1 < self.items.len() => i + 1,
+			_ => 0,
+		};
+		self.state.select(Some(i));
+	}
+
 	pub fn previous(&mut self) {
 		let i = match self.state.selected() {
 			Some(i) => {
 				if i == 0 {
-					self.items.len() - 1
+					self.items.len().saturating_sub(1)
 				} else {
 					i - 1
 				}
@@ -57,60 +95,547 @@ pub struct App<'a> {
 	pub index: usize,
 	pub post_scroll: u16,
 	pub comment_scroll: u16,
+	/// Sizes of the post text and the comments from the last draw, which
+	/// bound their scrolling.
+	pub post_extent: Extent,
+	pub comment_extent: Extent,
 	pub input: String,
+	/// The feed tabs. The feed being shown is `feeds[feed]`, but its state is
+	/// kept in the fields of `App` until another feed is shown.
+	pub feeds: Vec<Feed>,
+	pub feed: usize,
+	/// Where back and forward go in the feed shown. The other feeds keep
+	/// theirs in their entry.
+	pub history: History,
 	pub comments: serde_json::Value,
 	pub sort_by: String,
+	/// Time range of the top and controversial sorts, empty for reddit's default.
+	pub sort_time: String,
 	pub comments_sort_by: String,
+	/// Sorts new feeds and posts start with.
+	pub sorts: Sorts,
+	/// Posts left out of feeds.
+	pub filters: Filters,
 	pub current_focus: usize,
-	pub cookie: String,
+	/// The account used and its reddit_session cookie, empty when not
+	/// logged in.
+	pub credentials: Credentials,
+	/// Names of the accounts there are, for the picker and the status bar.
+	pub accounts: Vec<String>,
+	/// The accounts switched away from, by name.
+	sessions: HashMap<String, Session>,
+	pub username: Option<String>,
+	pub modhash: String,
+	pub viewing_hidden: bool,
+	pub selected_comment: usize,
+	pub dialog: Option<Dialog>,
+	pub moderation: Option<Moderation>,
+	pub highlighter: Highlighter,
+	pub code_scroll: u16,
+	pub images: Images,
+	pub thumbnails: bool,
+	pub list_offset: usize,
+	pub viewer: Option<Viewer>,
+	pub openers: Vec<Opener>,
+	pub link_picker: Option<LinkPicker>,
+	pub help: Option<Help>,
+	pub find: Option<Find>,
+	pub command_line: CommandLine,
+	pub command_line_open: bool,
+	pub theme: Theme,
+	pub panes: PaneSizes,
+	pub regions: Regions,
+	pub status: Status,
+	clicks: Clicks,
+	/// Whether the mouse is captured, which has to be restored after openers.
+	pub mouse: bool,
+	pub confirm_quit: bool,
+	/// The `clipboard` option, how text is copied.
+	pub clipboard: String,
+	pub keymap: Keymap,
+	/// Keys of a sequence or count that is still being typed.
+	keys: KeyState,
 }
 
 impl<'a> App<'a> {
-	pub fn new(cookie: String) -> App<'a> {
+	pub fn new(user_config: &UserConfig, credentials: Credentials) -> App<'a> {
+		let (theme, theme_errors) = Theme::load(
+			&user_config.theme,
+			&user_config.colors,
+			ColorSupport::detect(&user_config.color_mode),
+		);
+		let (keymap, key_errors) = Keymap::load(&user_config.keymap, &user_config.keys);
+		let errors: Vec<String> = user_config
+			.messages
+			.iter()
+			.cloned()
+			.chain(theme_errors)
+			.chain(key_errors)
+			.collect();
+		let sorts = &user_config.sort;
 		App {
-			titles: vec!["Home", "Post", "Search"],
+			titles: vec!["Home", "Post", "Search", "Mod"],
 			current_focus: 0,
 			index: 0,
 			items: StatefulList::with_items(vec![("Item0".to_string(), 1)]),
 			post_scroll: 0,
+			post_extent: Extent::default(),
+			comment_extent: Extent::default(),
 			input: "".to_owned(),
+			feeds: user_config
+				.feeds(&credentials.account)
+				.iter()
+				.map(|x| Feed::new(x, sorts))
+				.collect(),
+			feed: 0,
+			history: History::default(),
 			comments: serde_json::from_str("{\"foo\":\"bar\"}").unwrap(),
-			sort_by: "hot".to_string(),
-			comments_sort_by: "best".to_string(),
+			sort_by: sorts.posts.clone(),
+			sort_time: sorts.time.clone(),
+			comments_sort_by: sorts.comments.clone(),
+			sorts: sorts.clone(),
+			filters: user_config.filters.clone(),
 			comment_scroll: 0,
-			cookie: cookie,
+			accounts: accounts::names(user_config, [&credentials.account]),
+			credentials,
+			sessions: HashMap::new(),
+			username: None,
+			modhash: "".to_string(),
+			viewing_hidden: false,
+			selected_comment: 0,
+			dialog: match errors.is_empty() {
+				true => None,
+				false => Some(Dialog::Message(errors.join("\n"))),
+			},
+			moderation: None,
+			highlighter: Highlighter::new(&user_config.code_theme),
+			code_scroll: 0,
+			images: Images::new(user_config.media.images),
+			thumbnails: user_config.media.thumbnails,
+			list_offset: 0,
+			viewer: None,
+			openers: user_config.openers.clone(),
+			link_picker: None,
+			help: None,
+			find: None,
+			command_line: CommandLine::default(),
+			command_line_open: false,
+			theme,
+			panes: user_config.panes.clone(),
+			regions: Regions::default(),
+			status: Status::new(),
+			clicks: Clicks::default(),
+			mouse: user_config.mouse,
+			confirm_quit: user_config.confirm_quit,
+			clipboard: user_config.clipboard.clone(),
+			keymap,
+			keys: KeyState::default(),
 		}
 	}
-	pub fn post_scroll_up(&mut self, ammount: u16) {
-		if self.post_scroll >= 1 {
-			self.post_scroll -= ammount;
+	pub fn load_identity(&mut self) {
+		if let Some(me) = utils::get_me(self.credentials.cookie.expose()) {
+			self.username = me["name"].as_str().map(|x| x.to_string());
+			self.modhash = me["modhash"].as_str().unwrap_or_default().to_string();
 		}
 	}
+	pub fn post_scroll_up(&mut self, ammount: u16) {
+		self.post_scroll = self.post_scroll.saturating_sub(ammount);
+	}
 	pub fn post_scroll_down(&mut self, ammount: u16) {
-		self.post_scroll += ammount;
+		self.post_scroll = (self.post_scroll + ammount).min(self.post_extent.max_scroll());
 	}
 	pub fn comment_scroll_up(&mut self, ammount: u16) {
-		if self.comment_scroll >= 1 {
-			self.comment_scroll -= ammount;
-		}
+		self.comment_scroll = self.comment_scroll.saturating_sub(ammount);
 	}
 	pub fn comment_scroll_down(&mut self, ammount: u16) {
-		self.comment_scroll += ammount;
+		self.comment_scroll = (self.comment_scroll + ammount).min(self.comment_extent.max_scroll());
+	}
+	/// Scrolls the focused pane of the Post tab by a page or half a page.
+	pub fn page_pane(&mut self, command: Command) {
+		let half = matches!(command, Command::HalfPageDown | Command::HalfPageUp);
+		let down = matches!(command, Command::PageDown | Command::HalfPageDown);
+		match (self.current_focus, down) {
+			(0, true) => self.post_scroll_down(self.post_extent.page(half)),
+			(0, false) => self.post_scroll_up(self.post_extent.page(half)),
+			(_, true) => self.comment_scroll_down(self.comment_extent.page(half)),
+			(_, false) => self.comment_scroll_up(self.comment_extent.page(half)),
+		}
+	}
+	/// Moves the selection of the post list by a page or half a page. Paging
+	/// down from the last post loads the next page of the feed.
+	pub fn page_list(
+		&mut self,
+		command: Command,
+		v: &mut Vec<serde_json::Value>,
+		last_post_id: &mut String,
+	) {
+		let rows = (self.regions.list.height / self.regions.list_row_height.max(1)).max(1) as usize;
+		let rows = match command {
+			Command::HalfPageDown | Command::HalfPageUp => (rows / 2).max(1),
+			_ => rows,
+		};
+		let selected = self.items.state.selected().unwrap_or(0);
+		let last = v.len().saturating_sub(1);
+		match command {
+			Command::PageDown | Command::HalfPageDown if selected >= last => {
+				self.select_next_post(v, last_post_id)
+			}
+			Command::PageDown | Command::HalfPageDown => {
+				self.select_post((selected + rows).min(last), v)
+			}
+			_ => self.select_post(selected.saturating_sub(rows), v),
+		}
 	}
 
-	pub fn next(&mut self) {
-		self.comment_scroll = 0;
-		self.post_scroll = 0;
-		self.index = (self.index + 1) % self.titles.len();
+	pub fn code_scroll_left(&mut self, ammount: u16) {
+		self.code_scroll = self.code_scroll.saturating_sub(ammount);
+	}
+	pub fn code_scroll_right(&mut self, ammount: u16) {
+		let columns = self.post_extent.columns.max(self.comment_extent.columns);
+		self.code_scroll = self.code_scroll.saturating_add(ammount).min(columns);
 	}
 
-	pub fn previous(&mut self) {
+	/// Grows or shrinks the post list or the post pane and saves the new sizes.
+	pub fn resize_pane(&mut self, command: Command) {
+		match command {
+			Command::ShrinkList => self.panes.resize_list(-5),
+			Command::GrowList => self.panes.resize_list(5),
+			Command::ShrinkPost => self.panes.resize_post(-5),
+			Command::GrowPost => self.panes.resize_post(5),
+			_ => return,
+		}
+		if let Err(err) = UserConfig::change_panes(&self.panes) {
+			self.dialog = Some(Dialog::Message(err));
+		}
+	}
+
+	pub fn next_tab(&mut self, v: &[serde_json::Value]) {
+		self.select_tab((self.index + 1) % self.titles.len(), v);
+	}
+
+	pub fn previous_tab(&mut self, v: &[serde_json::Value]) {
+		self.select_tab((self.index + self.titles.len() - 1) % self.titles.len(), v);
+	}
+	/// Where keys are looked up: the open overlay, or else the current tab.
+	pub fn key_context(&self) -> Context {
+		if self.command_line_open {
+			return Context::CommandLine;
+		}
+		if self.help.is_some() {
+			return Context::Help;
+		}
+		if self.find.as_ref().is_some_and(|x| x.typing) {
+			return Context::Find;
+		}
+		match (
+			self.link_picker.is_some(),
+			self.viewer.is_some(),
+			self.index,
+		) {
+			(true, _, _) => Context::Links,
+			(_, true, _) => Context::Viewer,
+			(_, _, 0) => Context::Home,
+			(_, _, 1) => Context::Post,
+			(_, _, 2) => Context::Search,
+			_ => Context::Mod,
+		}
+	}
+	pub fn command_line_command(&mut self, command: Command) {
+		match command {
+			Command::Close => self.command_line_open = false,
+			Command::DeleteChar => self.command_line.delete_char(),
+			Command::Complete => self.command_line.complete(),
+			Command::PreviousItem => self.command_line.history_previous(),
+			Command::NextItem => self.command_line.history_next(),
+			_ => {}
+		}
+	}
+	/// Changes an option for this session, like `:set theme light`.
+	pub fn set(&mut self, option: &str, value: &str) -> Result<(), String> {
+		let switch = match value {
+			"on" | "true" => Ok(true),
+			"off" | "false" => Ok(false),
+			_ => Err(format!("{}{}", "Use on or off for ", option)),
+		};
+		let config = UserConfig::load();
+		match option {
+			"theme" => {
+				let (theme, errors) = Theme::load(
+					value,
+					&config.colors,
+					ColorSupport::detect(&config.color_mode),
+				);
+				if !errors.is_empty() {
+					return Err(errors.join("\n"));
+				}
+				self.theme = theme;
+			}
+			"keymap" => {
+				let (keymap, errors) = Keymap::load(value, &config.keys);
+				if !errors.is_empty() {
+					return Err(errors.join("\n"));
+				}
+				self.keymap = keymap;
+			}
+			"clipboard" if !clipboard::BACKENDS.contains(&value) => {
+				return Err(format!(
+					"{}{}{}{}",
+					"Unknown clipboard: ",
+					value,
+					", choose one of ",
+					clipboard::BACKENDS.join(", ")
+				))
+			}
+			"clipboard" => self.clipboard = value.to_string(),
+			"thumbnails" => self.thumbnails = switch?,
+			"images" => self.images.enabled = switch?,
+			"mouse" => {
+				self.mouse = switch?;
+				let result = match self.mouse {
+					true => execute!(io::stdout(), EnableMouseCapture),
+					false => execute!(io::stdout(), DisableMouseCapture),
+				};
+				result.map_err(|err| err.to_string())?;
+			}
+			_ => {
+				return Err(format!(
+					"{}{}{}{}",
+					"Unknown option: ",
+					option,
+					", choose one of ",
+					command_line::OPTIONS.join(", ")
+				))
+			}
+		}
+		Ok(())
+	}
+	/// Logs in with a reddit_session cookie and saves it in the credentials file.
+	pub fn login(&mut self, cookie: &str) -> Result<(), String> {
+		self.credentials.save(Secret::new(cookie))?;
+		self.username = None;
+		self.moderation = None;
+		self.load_identity();
+		match &self.username {
+			Some(name) => {
+				self.status.toast(format!("{}{}", "Logged in as u/", name));
+				Ok(())
+			}
+			None => Err("Reddit didn't accept that cookie".to_string()),
+		}
+	}
+	fn account_names(&self) -> Vec<String> {
+		let open = self.sessions.keys().chain([&self.credentials.account]);
+		accounts::names(&UserConfig::load(), open)
+	}
+	/// Opens the account picker with the account used selected.
+	pub fn open_accounts(&mut self) {
+		self.accounts = self.account_names();
+		let current = user_config::account_name(&self.credentials.account);
+		let mut list = StatefulList::with_items(self.accounts.clone());
+		let selected = self.accounts.iter().position(|x| x == current);
+		list.state.select(Some(selected.unwrap_or(0)));
+		self.dialog = Some(Dialog::Accounts(list));
+	}
+	/// Switches to the account called `name`. The feeds, identity and
+	/// moderation state of the one used so far are put aside until it is
+	/// switched back to.
+	pub fn switch_account(
+		&mut self,
+		name: &str,
+		v: &mut Vec<serde_json::Value>,
+		last_post_id: &mut String,
+	) -> Result<(), String> {
+		let account = user_config::account_key(name);
+		if account == self.credentials.account {
+			return Ok(());
+		}
+		self.accounts = self.account_names();
+		if !self.accounts.iter().any(|x| x == name) {
+			return Err(format!(
+				"{}{}{}{}{}",
+				"No account called ", name, ", start rusddit with --account ", name, " to add it"
+			));
+		}
+		let session = match self.sessions.remove(account) {
+			Some(x) => x,
+			None => Session {
+				credentials: self.credentials.switch(account)?,
+				feeds: UserConfig::load()
+					.feeds(account)
+					.iter()
+					.map(|x| Feed::new(x, &self.sorts))
+					.collect(),
+				feed: 0,
+				username: None,
+				modhash: String::new(),
+				moderation: None,
+			},
+		};
+		self.feeds[self.feed] = Feed {
+			history: std::mem::take(&mut self.history),
+			..self.feed_state(std::mem::take(v), std::mem::take(last_post_id))
+		};
+		let old = Session {
+			credentials: std::mem::replace(&mut self.credentials, session.credentials),
+			feeds: std::mem::replace(&mut self.feeds, session.feeds),
+			feed: std::mem::replace(&mut self.feed, session.feed),
+			username: std::mem::replace(&mut self.username, session.username),
+			modhash: std::mem::replace(&mut self.modhash, session.modhash),
+			moderation: std::mem::replace(&mut self.moderation, session.moderation),
+		};
+		self.sessions.insert(old.credentials.account.clone(), old);
+		// Images may have been fetched with the other account's cookie
+		self.images.clear_cache();
+		self.viewer = None;
+		self.link_picker = None;
+		self.find = None;
+		self.index = 0;
+		let mut feed = std::mem::take(&mut self.feeds[self.feed]);
+		self.history = std::mem::take(&mut feed.history);
+		self.restore_feed(feed, v, last_post_id);
+		if self.username.is_none() {
+			self.load_identity();
+		}
+		self.status.toast(match &self.username {
+			Some(user) => format!("{}{}{}{}", "Switched to ", name, ", u/", user),
+			None => format!("{}{}{}", "Switched to ", name, ", not logged in"),
+		});
+		Ok(())
+	}
+	/// Starts a search of the focused pane.
+	pub fn open_find(&mut self) {
+		let pane = match (self.index, self.current_focus) {
+			(1, 0) => Pane::Post,
+			(1, _) => Pane::Comments,
+			_ => Pane::List,
+		};
+		self.find = Some(Find::new(pane));
+	}
+	pub fn find_command(&mut self, command: Command) {
+		let find = match &mut self.find {
+			Some(x) => x,
+			None => return,
+		};
+		match command {
+			Command::Close => self.find = None,
+			Command::Select if find.query.is_empty() => self.find = None,
+			Command::Select => find.typing = false,
+			Command::DeleteChar => find.delete_char(),
+			Command::ToggleCase => find.toggle_case(),
+			Command::ToggleRegex => find.toggle_regex(),
+			_ => {}
+		}
+	}
+	/// Goes to the next or previous match, focusing the pane it is in.
+	pub fn find_next(&mut self, forward: bool) {
+		let find = match self.find.as_mut().filter(|x| !x.query.is_empty()) {
+			Some(x) => x,
+			None => {
+				self.status
+					.toast("Nothing to find, press / to search".to_string());
+				return;
+			}
+		};
+		if find.count == 0 {
+			self.status.toast("No matches".to_string());
+			return;
+		}
+		match forward {
+			true => find.next(),
+			false => find.previous(),
+		}
+		match find.pane {
+			Pane::Post => self.current_focus = 0,
+			Pane::Comments => self.current_focus = 1,
+			_ => {}
+		}
+	}
+	pub fn help_command(&mut self, command: Command) {
+		let help = match &mut self.help {
+			Some(x) => x,
+			None => return,
+		};
+		match command {
+			Command::Close => self.help = None,
+			Command::NextItem => help.next(),
+			Command::PreviousItem => help.previous(),
+			Command::DeleteChar => help.delete_char(),
+			_ => {}
+		}
+	}
+	/// Switches to tab `index`, loading the comments when that is the Post tab.
+	pub fn select_tab(&mut self, index: usize, v: &[serde_json::Value]) {
+		if index == self.index || index >= self.titles.len() {
+			return;
+		}
+		if index == 1 && !v.is_empty() {
+			self.update_comments(v.to_vec(), self.items.state.selected().unwrap_or(0));
+		}
 		self.comment_scroll = 0;
 		self.post_scroll = 0;
-		if self.index > 0 {
-			self.index -= 1;
-		} else {
-			self.index = self.titles.len() - 1;
+		self.code_scroll = 0;
+		self.index = index;
+	}
+	/// Selects the next post, loading the next page after the last one. Stays on
+	/// the last post when there is no next page, like a followed thread.
+	pub fn select_next_post(&mut self, v: &mut Vec<serde_json::Value>, last_post_id: &mut String) {
+		self.items.next();
+		if self.items.state.selected() == Some(self.items.items.len()) {
+			match last_post_id.is_empty() && !v.is_empty() {
+				true => self.items.state.select(Some(v.len() - 1)),
+				false => {
+					*v = self.load_feed(true, last_post_id);
+					self.items.state.select(Some(0));
+				}
+			}
+		}
+		if self.index == 1 && !v.is_empty() {
+			self.update_comments(v.to_vec(), self.items.state.selected().unwrap_or(0));
+		}
+	}
+	pub fn select_previous_post(&mut self, v: &[serde_json::Value]) {
+		self.items.previous();
+		if self.index == 1 && !v.is_empty() {
+			self.update_comments(v.to_vec(), self.items.state.selected().unwrap_or(0));
+		}
+	}
+	/// Selects post `index`, loading its comments when on the Post tab.
+	pub fn select_post(&mut self, index: usize, v: &[serde_json::Value]) {
+		if index >= v.len() {
+			return;
+		}
+		self.items.state.select(Some(index));
+		if self.index == 1 {
+			self.update_comments(v.to_vec(), index);
+		}
+	}
+	/// Sorts the feed, or the comments when on the Post tab. `time` limits
+	/// the top and controversial sorts of the feed.
+	pub fn sort(
+		&mut self,
+		command: Command,
+		time: &str,
+		v: &mut Vec<serde_json::Value>,
+		last_post_id: &mut String,
+	) {
+		let sort = match command {
+			Command::SortHot => "hot",
+			Command::SortNew => "new",
+			Command::SortTop => "top",
+			Command::SortRising => "rising",
+			Command::SortBest => "best",
+			Command::SortControversial => "controversial",
+			_ => return,
+		}
+		.to_string();
+		if self.index != 1 {
+			self.sort_by = sort;
+			self.sort_time = time.to_string();
+			*v = self.load_feed(false, last_post_id);
+		} else if sort != "rising" && !v.is_empty() {
+			// Comments can't be sorted by rising
+			self.comments_sort_by = sort;
+			self.update_comments(v.to_vec(), self.items.state.selected().unwrap_or(0));
 		}
 	}
 	pub fn append_input(&mut self, user_char: char) {
@@ -129,7 +654,7 @@ impl<'a> App<'a> {
 			"https://reddit.com/comments/", post_id, "/", self.comments_sort_by, ".json"
 		);
 		// println!("{}", url);
-		let body = match reqwest::blocking::get(&url) {
+		let body = match utils::send(utils::build_client("").get(&url)) {
 			Ok(x) => x,
 			Err(err) => {
 				panic!("called `Result::unwrap()` on an `Err` value: {:?}", err)
@@ -142,162 +667,1520 @@ impl<'a> App<'a> {
 			}
 		};
 		self.comments = serde_json::from_str(&body).unwrap();
+		self.selected_comment = 0;
+		self.code_scroll = 0;
 	}
 	pub fn change_focus(&mut self) {
 		self.current_focus = (self.current_focus + 1) % 2;
 	}
-}
-
-pub fn run_app<B: Backend>(
-	terminal: &mut Terminal<B>,
-	mut app: App,
-	mut v: Vec<serde_json::Value>,
-	mut last_post_id: &mut String,
-) -> io::Result<()> {
-	loop {
-		terminal.draw(|f| ui(f, &mut app, &mut v))?;
-		let reddit_cookie = app.cookie.clone();
-		if let Event::Key(key) = event::read()? {
-			if app.index == 0 {
-				match key.code {
-					KeyCode::Esc => return Ok(()),
-					KeyCode::Char('2') => app.next(),
-					KeyCode::Char('1') => app.previous(),
-					KeyCode::Left => app.previous(),
-					KeyCode::Up => app.items.previous(),
-					KeyCode::Char('h') => {
-						app.sort_by = "hot".to_string();
-						v = utils::get_posts(
-							app.input.clone(),
-							false,
-							app.sort_by.clone(),
-							&mut last_post_id,
-							reddit_cookie,
-						);
-					}
-					KeyCode::Char('b') => {
-						app.sort_by = "best".to_string();
-						v = utils::get_posts(
-							app.input.clone(),
-							false,
-							app.sort_by.clone(),
-							&mut last_post_id,
-							reddit_cookie,
-						);
-					}
-					KeyCode::Char('c') => {
-						app.sort_by = "controversial".to_string();
-						v = utils::get_posts(
-							app.input.clone(),
-							false,
-							app.sort_by.clone(),
-							&mut last_post_id,
-							reddit_cookie,
-						);
+	/// Loads the feed currently being browsed, either the subreddit in `input`
+	/// or the user's hidden posts. With `before` the next page is loaded.
+	pub fn load_feed(&mut self, before: bool, last_post_id: &mut String) -> Vec<serde_json::Value> {
+		self.status.page = match before {
+			true => self.status.page + 1,
+			false => 1,
+		};
+		let posts = match (&self.username, self.viewing_hidden) {
+			(Some(username), true) => utils::get_hidden_posts(
+				username,
+				before,
+				last_post_id,
+				self.credentials.cookie.expose().to_string(),
+			),
+			_ => utils::get_posts(
+				self.input.clone(),
+				before,
+				self.sort_by.clone(),
+				&self.sort_time,
+				last_post_id,
+				self.credentials.cookie.expose().to_string(),
+			),
+		};
+		posts
+			.into_iter()
+			.filter(|x| self.filters.allows(x))
+			.collect()
+	}
+	/// Copies part of the selected post or comment and says what was copied.
+	pub fn copy(&mut self, command: Command, v: &[serde_json::Value]) {
+		let post = match v.get(self.items.state.selected().unwrap_or(0)) {
+			Some(x) => &x["data"],
+			None => return,
+		};
+		// Comments are only loaded for the Post tab
+		let comments = self.comment_list();
+		let comment = match self.index {
+			1 => comments.get(self.selected_comment).map(|x| &x["data"]),
+			_ => None,
+		};
+		let permalink = |x: &serde_json::Value| {
+			x["permalink"]
+				.as_str()
+				.map(|x| format!("{}{}", "https://www.reddit.com", x))
+		};
+		let (what, text) = match command {
+			Command::CopyPermalink => ("permalink", permalink(post)),
+			Command::CopyUrl => ("link", opener::post_url(post)),
+			Command::CopyTitle => ("title", post["title"].as_str().map(decode_entities)),
+			Command::CopyText => (
+				"post text",
+				post["selftext"]
+					.as_str()
+					.filter(|x| !x.is_empty())
+					.map(decode_entities),
+			),
+			Command::CopyComment => (
+				"comment",
+				comment
+					.and_then(|x| x["body"].as_str())
+					.map(decode_entities),
+			),
+			Command::CopyCommentLink => ("comment link", comment.and_then(permalink)),
+			_ => return,
+		};
+		let text = match text {
+			Some(x) => x,
+			None => {
+				self.status
+					.toast(format!("{}{}", "Nothing to copy, there is no ", what));
+				return;
+			}
+		};
+		match clipboard::copy(&text, &self.clipboard) {
+			Ok(()) => self.status.toast(format!("{}{}", "Copied ", what)),
+			Err(err) => self.dialog = Some(Dialog::Message(err)),
+		}
+	}
+	/// Top level comments of the open post, without the trailing "load more" stub.
+	pub fn comment_list(&self) -> Vec<&serde_json::Value> {
+		match self.comments[1]["data"]["children"].as_array() {
+			Some(x) => x.iter().filter(|x| x["kind"] == "t1").collect(),
+			None => vec![],
+		}
+	}
+	pub fn next_comment(&mut self) {
+		if self.selected_comment + 1 < self.comment_list().len() {
+			self.selected_comment += 1;
+		}
+	}
+	pub fn previous_comment(&mut self) {
+		self.selected_comment = self.selected_comment.saturating_sub(1);
+	}
+	/// The post or, when the comments pane is focused on the Post tab, the
+	/// comment that actions like report and block apply to.
+	fn selected_thing(&self, v: &[serde_json::Value]) -> Option<serde_json::Value> {
+		if self.index == 1 && self.current_focus == 1 {
+			return self
+				.comment_list()
+				.get(self.selected_comment)
+				.map(|x| x["data"].clone());
+		}
+		v.get(self.items.state.selected()?)
+			.map(|x| x["data"].clone())
+	}
+	pub fn confirm_hide(&mut self, v: &[serde_json::Value]) {
+		let post = match v.get(self.items.state.selected().unwrap_or(0)) {
+			Some(x) => x,
+			None => return,
+		};
+		let id = post["data"]["name"]
+			.as_str()
+			.unwrap_or_default()
+			.to_string();
+		let action = match self.viewing_hidden {
+			true => Action::Unhide(id),
+			false => Action::Hide(id),
+		};
+		self.dialog = Some(Dialog::Confirm { action });
+	}
+	pub fn confirm_block(&mut self, v: &[serde_json::Value]) {
+		let thing = match self.selected_thing(v) {
+			Some(x) => x,
+			None => return,
+		};
+		let author = thing["author"].as_str().unwrap_or_default();
+		if author.is_empty() || author == "[deleted]" {
+			self.dialog = Some(Dialog::Message("This account is deleted".to_string()));
+			return;
+		}
+		self.dialog = Some(Dialog::Confirm {
+			action: Action::Block(author.to_string()),
+		});
+	}
+	pub fn choose_report_reason(&mut self, v: &[serde_json::Value]) {
+		let thing = match self.selected_thing(v) {
+			Some(x) => x,
+			None => return,
+		};
+		let subreddit = thing["subreddit"].as_str().unwrap_or_default();
+		self.dialog = Some(
+			match utils::get_report_reasons(subreddit, self.credentials.cookie.expose()) {
+				Ok(reasons) if !reasons.is_empty() => Dialog::Report {
+					thing_id: thing["name"].as_str().unwrap_or_default().to_string(),
+					reasons: StatefulList::with_items(reasons),
+				},
+				Ok(_) => Dialog::Message("This subreddit has no report reasons".to_string()),
+				Err(err) => Dialog::Message(err),
+			},
+		);
+	}
+	/// Shows the feed tabs from the config, or the front page without any.
+	/// `startup` is the feed asked for on the command line, which is shown
+	/// first and gets a tab if it has none. Returns the posts of the shown feed.
+	pub fn open_feeds(
+		&mut self,
+		startup: &str,
+		last_post_id: &mut String,
+	) -> Vec<serde_json::Value> {
+		if !startup.is_empty() {
+			self.feed = match self.feeds.iter().position(|x| x.input == startup) {
+				Some(i) => i,
+				None => {
+					self.feeds.push(Feed::new(startup, &self.sorts));
+					self.feeds.len() - 1
+				}
+			};
+		}
+		self.input = self.feeds[self.feed].input.clone();
+		self.load_feed(false, last_post_id)
+	}
+	/// Names of the feed tabs, the shown one as it is now.
+	pub fn feed_titles(&self) -> Vec<String> {
+		self.feed_inputs()
+			.iter()
+			.enumerate()
+			.map(|(i, x)| match i == self.feed && self.viewing_hidden {
+				true => "hidden".to_string(),
+				false => feeds::title(x),
+			})
+			.collect()
+	}
+	fn feed_inputs(&self) -> Vec<String> {
+		self.feeds
+			.iter()
+			.enumerate()
+			.map(|(i, x)| match i == self.feed {
+				true => self.input.clone(),
+				false => x.input.clone(),
+			})
+			.collect()
+	}
+	/// Saves the feed tabs so the next session starts with them.
+	fn save_feeds(&mut self) {
+		let tabs = self.feed_inputs();
+		if let Err(err) = UserConfig::load().change_tabs(&self.credentials.account, tabs) {
+			self.dialog = Some(Dialog::Message(err));
+		}
+	}
+	/// Shows feed tab `index`, keeping the state of the one shown so far.
+	pub fn switch_feed(
+		&mut self,
+		index: usize,
+		v: &mut Vec<serde_json::Value>,
+		last_post_id: &mut String,
+	) {
+		if index == self.feed || index >= self.feeds.len() {
+			return;
+		}
+		self.feeds[self.feed] = Feed {
+			history: std::mem::take(&mut self.history),
+			..self.feed_state(std::mem::take(v), std::mem::take(last_post_id))
+		};
+		let mut feed = std::mem::take(&mut self.feeds[index]);
+		self.feed = index;
+		self.history = std::mem::take(&mut feed.history);
+		// The input stays in the entry so the feed list can still be saved
+		self.feeds[index].input = feed.input.clone();
+		self.restore_feed(feed, v, last_post_id);
+		if self.index == 1 {
+			self.index = 0;
+		}
+	}
+	/// The state of the feed shown, with `posts` and `last_post_id` as its
+	/// posts and cursor.
+	fn feed_state(&self, posts: Vec<serde_json::Value>, last_post_id: String) -> Feed {
+		Feed {
+			input: self.input.clone(),
+			sort_by: self.sort_by.clone(),
+			sort_time: self.sort_time.clone(),
+			viewing_hidden: self.viewing_hidden,
+			posts,
+			selected: self.items.state.selected(),
+			list_offset: self.list_offset,
+			last_post_id,
+			page: self.status.page,
+			loaded: true,
+			history: History::default(),
+		}
+	}
+	/// Shows `feed` where it was left, loading it if it wasn't shown before.
+	fn restore_feed(
+		&mut self,
+		feed: Feed,
+		v: &mut Vec<serde_json::Value>,
+		last_post_id: &mut String,
+	) {
+		self.input = feed.input;
+		self.sort_by = feed.sort_by;
+		self.sort_time = feed.sort_time;
+		self.viewing_hidden = feed.viewing_hidden;
+		self.list_offset = feed.list_offset;
+		self.items.state.select(Some(feed.selected.unwrap_or(0)));
+		*last_post_id = feed.last_post_id;
+		self.status.page = feed.page;
+		*v = match feed.loaded {
+			true => feed.posts,
+			false => self.load_feed(false, last_post_id),
+		};
+	}
+	fn view(&self, v: &[serde_json::Value], last_post_id: &str) -> View {
+		View {
+			feed: self.feed_state(v.to_vec(), last_post_id.to_string()),
+			index: self.index,
+			comments: self.comments.clone(),
+			comments_sort_by: self.comments_sort_by.clone(),
+			post_scroll: self.post_scroll,
+			comment_scroll: self.comment_scroll,
+			code_scroll: self.code_scroll,
+			selected_comment: self.selected_comment,
+			current_focus: self.current_focus,
+		}
+	}
+	fn show_view(&mut self, view: View, v: &mut Vec<serde_json::Value>, last_post_id: &mut String) {
+		self.restore_feed(view.feed, v, last_post_id);
+		self.index = view.index;
+		self.comments = view.comments;
+		self.comments_sort_by = view.comments_sort_by;
+		self.post_scroll = view.post_scroll;
+		self.comment_scroll = view.comment_scroll;
+		self.code_scroll = view.code_scroll;
+		self.selected_comment = view.selected_comment;
+		self.current_focus = view.current_focus;
+	}
+	/// Remembers the view shown before going somewhere else, so back can
+	/// return to it.
+	pub fn remember(&mut self, v: &[serde_json::Value], last_post_id: &str) {
+		let view = self.view(v, last_post_id);
+		self.history.push(view);
+	}
+	pub fn go_back(&mut self, v: &mut Vec<serde_json::Value>, last_post_id: &mut String) {
+		match self.history.back(self.view(v, last_post_id)) {
+			Some(view) => self.show_view(view, v, last_post_id),
+			// Esc used to quit, so say how to quit now
+			None => self
+				.status
+				.toast(match self.keymap.keys_for(Context::Global, Command::Quit) {
+					Some(keys) => {
+						format!("{}{}{}", "Nothing to go back to, press ", keys, " to quit")
 					}
-					KeyCode::Right => {
-						app.update_comments(v.clone(), app.items.state.selected().unwrap());
-						app.next();
+					None => "Nothing to go back to".to_string(),
+				}),
+		}
+	}
+	pub fn go_forward(&mut self, v: &mut Vec<serde_json::Value>, last_post_id: &mut String) {
+		match self.history.forward(self.view(v, last_post_id)) {
+			Some(view) => self.show_view(view, v, last_post_id),
+			None => self.status.toast("Nothing to go forward to".to_string()),
+		}
+	}
+	/// Opens the selected post on the Post tab.
+	pub fn open_post(&mut self, v: &[serde_json::Value], last_post_id: &str) {
+		if self.index == 1 || v.is_empty() {
+			return;
+		}
+		self.remember(v, last_post_id);
+		self.select_tab(1, v);
+	}
+	/// Opens a feed in a new tab after the shown one.
+	pub fn open_feed(
+		&mut self,
+		input: &str,
+		v: &mut Vec<serde_json::Value>,
+		last_post_id: &mut String,
+	) {
+		self.feeds
+			.insert(self.feed + 1, Feed::new(input, &self.sorts));
+		self.switch_feed(self.feed + 1, v, last_post_id);
+		self.save_feeds();
+	}
+	pub fn close_feed(&mut self, v: &mut Vec<serde_json::Value>, last_post_id: &mut String) {
+		if self.feeds.len() == 1 {
+			self.dialog = Some(Dialog::Message("This is the last feed".to_string()));
+			return;
+		}
+		let closed = self.feed;
+		let next = match closed + 1 < self.feeds.len() {
+			true => closed + 1,
+			false => closed - 1,
+		};
+		self.switch_feed(next, v, last_post_id);
+		self.feeds.remove(closed);
+		if next > closed {
+			self.feed -= 1;
+		}
+		self.save_feeds();
+	}
+	/// Moves the shown feed tab to `position`, counted from 0.
+	pub fn move_feed(&mut self, position: usize) {
+		let position = position.min(self.feeds.len() - 1);
+		let feed = self.feeds.remove(self.feed);
+		self.feeds.insert(position, feed);
+		self.feed = position;
+		self.save_feeds();
+	}
+	pub fn toggle_hidden_feed(
+		&mut self,
+		last_post_id: &mut String,
+	) -> Option<Vec<serde_json::Value>> {
+		if self.username.is_none() {
+			self.dialog = Some(Dialog::Message(
+				"You need to be logged in to see hidden posts".to_string(),
+			));
+			return None;
+		}
+		self.viewing_hidden = !self.viewing_hidden;
+		self.items.state.select(Some(0));
+		Some(self.load_feed(false, last_post_id))
+	}
+	/// Handles a key while a dialog is open. Returns whether rusddit should quit.
+	pub fn handle_dialog_key(
+		&mut self,
+		code: KeyCode,
+		v: &mut Vec<serde_json::Value>,
+		last_post_id: &mut String,
+	) -> bool {
+		let dialog = match self.dialog.take() {
+			Some(x) => x,
+			None => return false,
+		};
+		self.dialog = match dialog {
+			Dialog::Confirm { action } => match code {
+				KeyCode::Char('y') | KeyCode::Enter => self.perform(action, v),
+				KeyCode::Char('n') | KeyCode::Esc => None,
+				_ => Some(Dialog::Confirm { action }),
+			},
+			Dialog::Report {
+				thing_id,
+				mut reasons,
+			} => match code {
+				KeyCode::Esc => None,
+				KeyCode::Enter => {
+					let reason = reasons.items[reasons.state.selected().unwrap_or(0)].clone();
+					Some(Dialog::Confirm {
+						action: Action::Report { thing_id, reason },
+					})
+				}
+				_ => {
+					match code {
+						KeyCode::Up => reasons.previous(),
+						KeyCode::Down => reasons.cycle_next(),
+						_ => {}
 					}
-					KeyCode::Down => {
-						app.items.next();
-						if app.items.state.selected() == Some(app.items.items.len()) {
-							v = utils::get_posts(
-								app.input.clone(),
-								true,
-								app.sort_by.clone(),
-								&mut last_post_id,
-								reddit_cookie,
-							);
-							app.items.state.select(Some(0));
-						}
+					Some(Dialog::Report { thing_id, reasons })
+				}
+			},
+			Dialog::RemovalReason {
+				thing_id,
+				reason_ids,
+				mut reasons,
+			} => match code {
+				KeyCode::Esc => None,
+				KeyCode::Enter => {
+					let selected = reasons.state.selected().unwrap_or(0);
+					let reason = match selected {
+						0 => None,
+						_ => Some((
+							reason_ids[selected - 1].clone(),
+							reasons.items[selected].clone(),
+						)),
+					};
+					Some(Dialog::Confirm {
+						action: Action::Remove {
+							thing_id,
+							spam: false,
+							reason,
+						},
+					})
+				}
+				_ => {
+					match code {
+						KeyCode::Up => reasons.previous(),
+						KeyCode::Down => reasons.cycle_next(),
+						_ => {}
 					}
-					_ => {}
+					Some(Dialog::RemovalReason {
+						thing_id,
+						reason_ids,
+						reasons,
+					})
 				}
-			} else if app.index == 1 {
-				match key.code {
-					KeyCode::Esc => return Ok(()),
-					KeyCode::Tab => {
-						app.change_focus();
+			},
+			Dialog::Accounts(mut accounts) => match code {
+				KeyCode::Esc => None,
+				KeyCode::Enter => {
+					let name = accounts.items[accounts.state.selected().unwrap_or(0)].clone();
+					self.switch_account(&name, v, last_post_id)
+						.err()
+						.map(Dialog::Message)
+				}
+				_ => {
+					match code {
+						KeyCode::Up => accounts.previous(),
+						KeyCode::Down => accounts.cycle_next(),
+						_ => {}
 					}
-					KeyCode::Char('2') => app.next(),
-					KeyCode::Char('1') => app.previous(),
-					KeyCode::Left => app.previous(),
-					KeyCode::Char('j') => {
-						if app.current_focus == 0 {
-							app.post_scroll_up(1);
-						} else {
-							app.comment_scroll_up(1);
-						}
+					Some(Dialog::Accounts(accounts))
+				}
+			},
+			Dialog::Quit => match code {
+				KeyCode::Char('y') | KeyCode::Enter => return true,
+				KeyCode::Char('n') | KeyCode::Esc => None,
+				_ => Some(Dialog::Quit),
+			},
+			Dialog::Message(_) => None,
+		};
+		false
+	}
+	fn perform(&mut self, action: Action, v: &mut Vec<serde_json::Value>) -> Option<Dialog> {
+		let result = match &action {
+			Action::Hide(id) => utils::post_api(
+				"/api/hide",
+				&[("id", id)],
+				&self.modhash,
+				self.credentials.cookie.expose(),
+			),
+			Action::Unhide(id) => utils::post_api(
+				"/api/unhide",
+				&[("id", id)],
+				&self.modhash,
+				self.credentials.cookie.expose(),
+			),
+			Action::Report { thing_id, reason } => utils::post_api(
+				"/api/report",
+				&[("thing_id", thing_id), ("reason", reason)],
+				&self.modhash,
+				self.credentials.cookie.expose(),
+			),
+			Action::Block(author) => utils::post_api(
+				"/api/block_user",
+				&[("name", author)],
+				&self.modhash,
+				self.credentials.cookie.expose(),
+			),
+			Action::Approve(id) => utils::post_api(
+				"/api/approve",
+				&[("id", id)],
+				&self.modhash,
+				self.credentials.cookie.expose(),
+			),
+			Action::Remove {
+				thing_id,
+				spam,
+				reason,
+			} => utils::post_api(
+				"/api/remove",
+				&[("id", thing_id), ("spam", &spam.to_string())],
+				&self.modhash,
+				self.credentials.cookie.expose(),
+			)
+			.and_then(|x| match reason {
+				Some((reason_id, _)) => utils::post_api(
+					"/api/v1/modactions/removal_reasons",
+					&[(
+						"json",
+						&serde_json::json!({
+							"item_ids": [thing_id],
+							"mod_note": null,
+							"reason_id": reason_id,
+						})
+						.to_string(),
+					)],
+					&self.modhash,
+					self.credentials.cookie.expose(),
+				),
+				None => Ok(x),
+			}),
+			Action::Lock(id, state) => utils::post_api(
+				match state {
+					true => "/api/lock",
+					false => "/api/unlock",
+				},
+				&[("id", id)],
+				&self.modhash,
+				self.credentials.cookie.expose(),
+			),
+			// Comments can only be stickied by distinguishing them
+			Action::Sticky { thing_id, state } if thing_id.starts_with("t1_") => utils::post_api(
+				"/api/distinguish",
+				&[
+					("id", thing_id),
+					("how", "yes"),
+					("sticky", &state.to_string()),
+				],
+				&self.modhash,
+				self.credentials.cookie.expose(),
+			),
+			Action::Sticky { thing_id, state } => utils::post_api(
+				"/api/set_subreddit_sticky",
+				&[("id", thing_id), ("state", &state.to_string())],
+				&self.modhash,
+				self.credentials.cookie.expose(),
+			),
+			Action::Distinguish(id, state) => utils::post_api(
+				"/api/distinguish",
+				&[
+					("id", id),
+					(
+						"how",
+						match state {
+							true => "yes",
+							false => "no",
+						},
+					),
+				],
+				&self.modhash,
+				self.credentials.cookie.expose(),
+			),
+		};
+		if let Err(err) = result {
+			return Some(Dialog::Message(err));
+		}
+		let hidden = matches!(action, Action::Hide(_));
+		match action {
+			Action::Hide(id) | Action::Unhide(id) => {
+				v.retain(|x| x["data"]["name"] != id.as_str());
+				self.after_posts_removed(v);
+				self.status.toast(
+					match hidden {
+						true => "Post hidden",
+						false => "Post unhidden",
 					}
-					KeyCode::Right => app.next(),
-					KeyCode::Char('k') => {
-						if app.current_focus == 0 {
-							app.post_scroll_down(1);
-						} else {
-							app.comment_scroll_down(1);
-						}
+					.to_string(),
+				);
+			}
+			Action::Report { .. } => self.status.toast("Thanks for reporting".to_string()),
+			Action::Block(author) => {
+				v.retain(|x| x["data"]["author"] != author.as_str());
+				self.after_posts_removed(v);
+				self.status
+					.toast(format!("{}{}{}", "u/", author, " is blocked"));
+			}
+			Action::Approve(id) => {
+				if let Some(moderation) = &mut self.moderation {
+					moderation.remove_item(&id);
+				}
+				self.status.toast("Approved".to_string());
+			}
+			Action::Remove { thing_id, spam, .. } => {
+				if let Some(moderation) = &mut self.moderation {
+					moderation.remove_item(&thing_id);
+				}
+				self.status.toast(
+					match spam {
+						true => "Removed as spam",
+						false => "Removed",
 					}
-					KeyCode::Char('h') => {
-						app.comments_sort_by = "hot".to_string();
-						app.update_comments(v.to_owned(), app.items.state.selected().unwrap());
+					.to_string(),
+				);
+			}
+			Action::Lock(id, state) => {
+				self.update_mod_item(&id, "locked", serde_json::Value::Bool(state));
+				self.status.toast(
+					match state {
+						true => "Locked",
+						false => "Unlocked",
 					}
-					KeyCode::Char('b') => {
-						app.comments_sort_by = "best".to_string();
-						app.update_comments(v.to_owned(), app.items.state.selected().unwrap());
+					.to_string(),
+				);
+			}
+			Action::Sticky { thing_id, state } => {
+				self.update_mod_item(&thing_id, "stickied", serde_json::Value::Bool(state));
+				if thing_id.starts_with("t1_") {
+					self.update_mod_item(&thing_id, "distinguished", "moderator".into());
+				}
+				self.status.toast(
+					match state {
+						true => "Stickied",
+						false => "Unstickied",
 					}
-					KeyCode::Char('c') => {
-						app.comments_sort_by = "controversial".to_string();
-						app.update_comments(v.to_owned(), app.items.state.selected().unwrap());
+					.to_string(),
+				);
+			}
+			Action::Distinguish(id, state) => {
+				let distinguished = match state {
+					true => "moderator".into(),
+					false => serde_json::Value::Null,
+				};
+				self.update_mod_item(&id, "distinguished", distinguished);
+				self.status.toast(
+					match state {
+						true => "Distinguished",
+						false => "Undistinguished",
 					}
-					KeyCode::Down => {
-						app.items.next();
-						if app.items.state.selected() == Some(app.items.items.len()) {
-							v = utils::get_posts(
-								app.input.clone(),
-								true,
-								app.sort_by.clone(),
-								&mut last_post_id,
-								reddit_cookie,
-							);
-							app.items.state.select(Some(0));
-						}
-						app.update_comments(v.to_owned(), app.items.state.selected().unwrap());
+					.to_string(),
+				);
+			}
+		}
+		None
+	}
+	pub fn open_viewer(&mut self, v: &[serde_json::Value]) {
+		let selected = self.items.state.selected().unwrap_or(0);
+		self.viewer = Viewer::open(v, selected);
+		if self.viewer.is_none() {
+			self.dialog = Some(Dialog::Message("This post has no images".to_string()));
+		}
+	}
+	pub fn viewer_command(&mut self, command: Command, v: &[serde_json::Value]) {
+		let viewer = match &mut self.viewer {
+			Some(x) => x,
+			None => return,
+		};
+		match command {
+			Command::Close => {
+				// Leave the Post tab on whatever post the viewer ended up on
+				let post = viewer.post;
+				self.viewer = None;
+				if self.items.state.selected() != Some(post) {
+					self.items.state.select(Some(post));
+					self.update_comments(v.to_vec(), post);
+					self.post_scroll = 0;
+					self.comment_scroll = 0;
+				}
+			}
+			Command::NextImage => viewer.next_image(v),
+			Command::PreviousImage => viewer.previous_image(v),
+			Command::NextPost => {
+				viewer.next_post(v);
+			}
+			Command::PreviousPost => {
+				viewer.previous_post(v, false);
+			}
+			Command::ZoomIn => viewer.zoom_in(),
+			Command::ZoomOut => viewer.zoom_out(),
+			Command::ResetZoom => viewer.reset_zoom(),
+			Command::PanLeft => viewer.pan(-1, 0),
+			Command::PanRight => viewer.pan(1, 0),
+			Command::PanUp => viewer.pan(0, -1),
+			Command::PanDown => viewer.pan(0, 1),
+			Command::ToggleImages => self.images.toggle(),
+			_ => {}
+		}
+	}
+	/// Opens the link picker with the links of the open post and the selected
+	/// comment.
+	pub fn open_link_picker(&mut self, v: &[serde_json::Value]) {
+		let post = match v.get(self.items.state.selected().unwrap_or(0)) {
+			Some(x) => &x["data"],
+			None => return,
+		};
+		let mut found = vec![];
+		if post["is_self"] != true {
+			if let Some(url) = opener::post_url(post) {
+				found.push(links::Link {
+					label: "Post link".to_string(),
+					url,
+				});
+			}
+		}
+		found.extend(links::extract_links(
+			post["selftext"].as_str().unwrap_or_default(),
+		));
+		if let Some(comment) = self.comment_list().get(self.selected_comment) {
+			found.extend(links::extract_links(
+				comment["data"]["body"].as_str().unwrap_or_default(),
+			));
+		}
+		match found.is_empty() {
+			true => self.dialog = Some(Dialog::Message("There are no links here".to_string())),
+			false => self.link_picker = Some(LinkPicker::new(found)),
+		}
+	}
+	/// Shows a subreddit, user or thread that a link pointed to.
+	pub fn follow(
+		&mut self,
+		target: Target,
+		v: &mut Vec<serde_json::Value>,
+		last_post_id: &mut String,
+	) {
+		match target {
+			Target::Subreddit(name) | Target::User(name) if name.is_empty() => {}
+			Target::Subreddit(name) => self.show_feed(format!("{}{}", "r/", name), v, last_post_id),
+			Target::User(name) => self.show_feed(
+				format!("{}{}{}", "user/", name, "/submitted"),
+				v,
+				last_post_id,
+			),
+			Target::Thread {
+				post_id,
+				comment_id,
+			} => {
+				let thread = utils::get_thread(
+					&post_id,
+					comment_id.as_deref(),
+					&self.comments_sort_by,
+					self.credentials.cookie.expose(),
+				);
+				match thread {
+					Ok(thread) => {
+						self.remember(v, last_post_id);
+						let post = thread[0]["data"]["children"][0].clone();
+						self.comments = thread;
+						self.post_scroll = 0;
+						self.comment_scroll = 0;
+						self.code_scroll = 0;
+						self.selected_comment = 0;
+						self.current_focus = match comment_id {
+							Some(_) => 1,
+							None => 0,
+						};
+						self.items.state.select(Some(0));
+						self.index = 1;
+						// The feed is now just this post, so there is no next page
+						last_post_id.clear();
+						*v = vec![post];
 					}
-					KeyCode::Up => {
-						app.items.previous();
-						app.update_comments(v.to_owned(), app.items.state.selected().unwrap());
+					Err(err) => self.dialog = Some(Dialog::Message(err)),
+				}
+			}
+		}
+	}
+	/// Shows the feed at `input` on Home, remembering the view left.
+	fn show_feed(
+		&mut self,
+		input: String,
+		v: &mut Vec<serde_json::Value>,
+		last_post_id: &mut String,
+	) {
+		self.remember(v, last_post_id);
+		self.input = input;
+		self.viewing_hidden = false;
+		self.current_focus = 0;
+		self.post_scroll = 0;
+		self.comment_scroll = 0;
+		self.code_scroll = 0;
+		self.items.state.select(Some(0));
+		self.index = 0;
+		*v = self.load_feed(false, last_post_id);
+	}
+	pub fn handle_mouse(
+		&mut self,
+		event: MouseEvent,
+		v: &mut Vec<serde_json::Value>,
+		last_post_id: &mut String,
+	) {
+		if self.dialog.is_some()
+			|| self.viewer.is_some()
+			|| self.link_picker.is_some()
+			|| self.help.is_some()
+		{
+			return;
+		}
+		let pane = match self.regions.pane_at(event.column, event.row) {
+			Some(x) => x,
+			None => return,
+		};
+		let click = event.kind == MouseEventKind::Down(MouseButton::Left);
+		match (event.kind, pane) {
+			(_, Pane::Feeds) if click => {
+				let titles = self.feed_titles();
+				let titles: Vec<&str> = titles.iter().map(|x| x.as_str()).collect();
+				if let Some(i) = mouse::tab_at(&titles, self.regions.feeds, event.column) {
+					self.switch_feed(i, v, last_post_id);
+				}
+			}
+			(_, Pane::Tabs) if click => {
+				if let Some(i) = mouse::tab_at(&self.titles, self.regions.tabs, event.column) {
+					self.select_tab(i, v);
+				}
+			}
+			(_, Pane::List) if click => {
+				let double = self.clicks.click(event.row);
+				let selected = self.list_offset + self.regions.list_row(event.row);
+				if selected >= v.len() {
+					return;
+				}
+				if self.items.state.selected() != Some(selected) {
+					self.items.state.select(Some(selected));
+					if self.index == 1 {
+						self.update_comments(v.to_vec(), selected);
 					}
+				}
+				if double {
+					self.open_post(v, last_post_id);
+				}
+			}
+			(_, Pane::Post) if click => self.current_focus = 0,
+			(_, Pane::Comments) if click => self.current_focus = 1,
+			(MouseEventKind::ScrollDown, Pane::List) => self.select_next_post(v, last_post_id),
+			(MouseEventKind::ScrollUp, Pane::List) => self.select_previous_post(v),
+			(MouseEventKind::ScrollDown, Pane::Post) => self.post_scroll_down(3),
+			(MouseEventKind::ScrollUp, Pane::Post) => self.post_scroll_up(3),
+			(MouseEventKind::ScrollDown, Pane::Comments) => self.comment_scroll_down(3),
+			(MouseEventKind::ScrollUp, Pane::Comments) => self.comment_scroll_up(3),
+			(kind, Pane::ModSubreddits | Pane::ModItems) => {
+				let moderation = match &mut self.moderation {
+					Some(x) => x,
+					None => return,
+				};
+				let list = match pane {
+					Pane::ModSubreddits => 0,
+					_ => 1,
+				};
+				match kind {
+					_ if click => moderation.current_focus = list,
+					MouseEventKind::ScrollDown if list == 0 => moderation.subreddits.cycle_next(),
+					MouseEventKind::ScrollUp if list == 0 => moderation.subreddits.previous(),
+					MouseEventKind::ScrollDown => moderation.items.cycle_next(),
+					MouseEventKind::ScrollUp => moderation.items.previous(),
 					_ => {}
 				}
-			} else if app.index == 2 {
-				match key.code {
-					KeyCode::Esc => return Ok(()),
-					KeyCode::Char(c) => {
-						app.append_input(c);
-					}
-					KeyCode::Backspace => {
-						app.delete_from_input();
+			}
+			_ => {}
+		}
+	}
+	fn update_mod_item(&mut self, id: &str, field: &str, value: serde_json::Value) {
+		if let Some(moderation) = &mut self.moderation {
+			moderation.update_item(id, field, value);
+		}
+	}
+	pub fn load_moderation(&mut self) {
+		let subreddits = match self.username {
+			Some(_) => utils::get_moderated_subreddits(self.credentials.cookie.expose()),
+			None => Err("You need to be logged in to moderate".to_string()),
+		};
+		self.moderation = Some(Moderation::new(match subreddits {
+			Ok(x) if x.is_empty() => {
+				self.dialog = Some(Dialog::Message(
+					"You don't moderate any subreddits".to_string(),
+				));
+				x
+			}
+			Ok(x) => x,
+			Err(err) => {
+				self.dialog = Some(Dialog::Message(err));
+				vec![]
+			}
+		}));
+	}
+	pub fn load_mod_listing(&mut self) {
+		if let Some(moderation) = &mut self.moderation {
+			if let Err(err) = moderation.load_items(self.credentials.cookie.expose()) {
+				self.dialog = Some(Dialog::Message(err));
+			}
+		}
+	}
+	pub fn mod_command(&mut self, command: Command) {
+		let moderation = match &mut self.moderation {
+			Some(x) => x,
+			None => return,
+		};
+		let subreddits = moderation.current_focus == 0;
+		let list = match subreddits {
+			true => &mut moderation.subreddits.state,
+			false => &mut moderation.items.state,
+		};
+		let last = match subreddits {
+			true => moderation.subreddits.items.len(),
+			false => moderation.items.items.len(),
+		}
+		.saturating_sub(1);
+		match command {
+			Command::FocusNext => moderation.current_focus = (moderation.current_focus + 1) % 2,
+			Command::NextListing => {
+				moderation.next_listing();
+				self.load_mod_listing();
+			}
+			Command::Top => list.select(Some(0)),
+			Command::Bottom => list.select(Some(last)),
+			Command::PreviousItem if subreddits => moderation.subreddits.previous(),
+			Command::NextItem if subreddits => moderation.subreddits.cycle_next(),
+			Command::Select if subreddits => {
+				moderation.current_focus = 1;
+				self.load_mod_listing();
+			}
+			Command::PreviousItem => moderation.items.previous(),
+			Command::NextItem => moderation.items.cycle_next(),
+			_ if !subreddits => self.moderate(command),
+			_ => {}
+		}
+	}
+	/// Runs a moderation action on the selected item of the Mod tab. Approving
+	/// and the toggles apply straight away, removals are confirmed first.
+	pub fn moderate(&mut self, command: Command) {
+		let item = match self.moderation.as_ref().and_then(|x| x.selected_item()) {
+			Some(x) => x.clone(),
+			None => return,
+		};
+		let id = item["name"].as_str().unwrap_or_default().to_string();
+		let action = match command {
+			Command::Approve => Action::Approve(id),
+			Command::Spam => {
+				self.dialog = Some(Dialog::Confirm {
+					action: Action::Remove {
+						thing_id: id,
+						spam: true,
+						reason: None,
+					},
+				});
+				return;
+			}
+			Command::Remove => {
+				let subreddit = item["subreddit"].as_str().unwrap_or_default();
+				self.dialog = Some(
+					match utils::get_removal_reasons(subreddit, self.credentials.cookie.expose()) {
+						Ok(reasons) => {
+							let mut titles = vec!["No reason".to_string()];
+							titles.extend(reasons.iter().map(|x| x.1.clone()));
+							Dialog::RemovalReason {
+								thing_id: id,
+								reason_ids: reasons.into_iter().map(|x| x.0).collect(),
+								reasons: StatefulList::with_items(titles),
+							}
+						}
+						Err(err) => Dialog::Message(err),
+					},
+				);
+				return;
+			}
+			Command::Lock => Action::Lock(id, item["locked"] != true),
+			Command::Sticky => Action::Sticky {
+				thing_id: id,
+				state: item["stickied"] != true,
+			},
+			Command::Distinguish => Action::Distinguish(id, item["distinguished"].is_null()),
+			_ => return,
+		};
+		let mut v = vec![];
+		self.dialog = self.perform(action, &mut v);
+	}
+	fn after_posts_removed(&mut self, v: &[serde_json::Value]) {
+		let selected = self.items.state.selected().unwrap_or(0);
+		self.items
+			.state
+			.select(Some(selected.min(v.len().saturating_sub(1))));
+		if v.is_empty() {
+			self.index = 0;
+		} else if self.index == 1 {
+			self.update_comments(v.to_vec(), self.items.state.selected().unwrap_or(0));
+		}
+	}
+}
+
+/// Opens `url` with the first matching opener from the config. Openers that
+/// aren't detached get the terminal until they exit.
+pub fn open_url<B: Backend>(
+	terminal: &mut Terminal<B>,
+	app: &mut App,
+	url: &str,
+	post: Option<&serde_json::Value>,
+) -> io::Result<()> {
+	let result = match opener::command_for(&app.openers, url, post) {
+		Some((command, true)) => opener::run_detached(&command),
+		Some((command, false)) => {
+			app.images.clear()?;
+			opener::run_suspended(terminal, &command, app.mouse)?
+		}
+		None => Err("No opener is set up for this link".to_string()),
+	};
+	if let Err(err) = result {
+		app.dialog = Some(Dialog::Message(err));
+	}
+	Ok(())
+}
+
+/// Follows links into reddit inside rusddit and opens the rest with an opener.
+fn activate_link<B: Backend>(
+	terminal: &mut Terminal<B>,
+	app: &mut App,
+	url: &str,
+	v: &mut Vec<serde_json::Value>,
+	last_post_id: &mut String,
+) -> io::Result<()> {
+	match links::reddit_target(url) {
+		Some(target) => {
+			app.follow(target, v, last_post_id);
+			Ok(())
+		}
+		None => open_url(terminal, app, url, None),
+	}
+}
+
+fn run_link_command<B: Backend>(
+	terminal: &mut Terminal<B>,
+	app: &mut App,
+	command: Command,
+	v: &mut Vec<serde_json::Value>,
+	last_post_id: &mut String,
+) -> io::Result<()> {
+	let picker = match &mut app.link_picker {
+		Some(x) => x,
+		None => return Ok(()),
+	};
+	match command {
+		Command::Close => app.link_picker = None,
+		Command::PreviousItem => picker.links.previous(),
+		Command::NextItem => picker.links.cycle_next(),
+		Command::OpenExternal => {
+			let url = picker.selected().map(|x| x.url.clone());
+			app.link_picker = None;
+			if let Some(url) = url {
+				open_url(terminal, app, &url, None)?;
+			}
+		}
+		Command::Copy => {
+			let url = picker.selected().map(|x| x.url.clone());
+			app.link_picker = None;
+			if let Some(url) = url {
+				match clipboard::copy(&url, &app.clipboard) {
+					Ok(()) => app.status.toast("Copied link".to_string()),
+					Err(err) => app.dialog = Some(Dialog::Message(err)),
+				}
+			}
+		}
+		Command::Select => follow_selected_link(terminal, app, v, last_post_id)?,
+		_ => {}
+	}
+	Ok(())
+}
+
+/// Typing hint keys selects a link once a whole hint is typed.
+fn type_link_hint<B: Backend>(
+	terminal: &mut Terminal<B>,
+	app: &mut App,
+	c: char,
+	v: &mut Vec<serde_json::Value>,
+	last_post_id: &mut String,
+) -> io::Result<()> {
+	match app.link_picker.as_mut().map(|x| x.type_hint(c)) {
+		Some(true) => follow_selected_link(terminal, app, v, last_post_id),
+		_ => Ok(()),
+	}
+}
+
+fn follow_selected_link<B: Backend>(
+	terminal: &mut Terminal<B>,
+	app: &mut App,
+	v: &mut Vec<serde_json::Value>,
+	last_post_id: &mut String,
+) -> io::Result<()> {
+	let url = app
+		.link_picker
+		.as_ref()
+		.and_then(|x| x.selected())
+		.map(|x| x.url.clone());
+	app.link_picker = None;
+	match url {
+		Some(url) => activate_link(terminal, app, &url, v, last_post_id),
+		None => Ok(()),
+	}
+}
+
+fn open_selected_post<B: Backend>(
+	terminal: &mut Terminal<B>,
+	app: &mut App,
+	v: &[serde_json::Value],
+) -> io::Result<()> {
+	let post = match v.get(app.items.state.selected().unwrap_or(0)) {
+		Some(x) => &x["data"],
+		None => return Ok(()),
+	};
+	match opener::post_url(post) {
+		Some(url) => open_url(terminal, app, &url, Some(post)),
+		None => Ok(()),
+	}
+}
+
+/// Runs a command on the Home, Post or Search tab.
+fn run_tab_command<B: Backend>(
+	terminal: &mut Terminal<B>,
+	app: &mut App,
+	command: Command,
+	v: &mut Vec<serde_json::Value>,
+	last_post_id: &mut String,
+) -> io::Result<()> {
+	let post_tab = app.index == 1;
+	match command {
+		Command::NextPost => app.select_next_post(v, last_post_id),
+		Command::PreviousPost => app.select_previous_post(v),
+		Command::OpenPost => app.open_post(v, last_post_id),
+		Command::Top if post_tab => match app.current_focus {
+			0 => app.post_scroll = 0,
+			_ => app.comment_scroll = 0,
+		},
+		Command::Bottom if post_tab => match app.current_focus {
+			0 => app.post_scroll = app.post_extent.max_scroll(),
+			_ => app.comment_scroll = app.comment_extent.max_scroll(),
+		},
+		Command::Top => app.select_post(0, v),
+		Command::Bottom if !post_tab => app.select_post(v.len().saturating_sub(1), v),
+		Command::SortHot
+		| Command::SortNew
+		| Command::SortTop
+		| Command::SortRising
+		| Command::SortBest
+		| Command::SortControversial => app.sort(command, "", v, last_post_id),
+		Command::ToggleHidden => {
+			if let Some(x) = app.toggle_hidden_feed(last_post_id) {
+				*v = x;
+			}
+		}
+		Command::Hide => app.confirm_hide(v),
+		Command::Report => app.choose_report_reason(v),
+		Command::Block => app.confirm_block(v),
+		Command::ToggleThumbnails => app.thumbnails = !app.thumbnails,
+		Command::OpenExternal => open_selected_post(terminal, app, v)?,
+		Command::FocusNext => app.change_focus(),
+		Command::ScrollDown => match app.current_focus {
+			0 => app.post_scroll_down(1),
+			_ => app.comment_scroll_down(1),
+		},
+		Command::ScrollUp => match app.current_focus {
+			0 => app.post_scroll_up(1),
+			_ => app.comment_scroll_up(1),
+		},
+		Command::ScrollLeft => app.code_scroll_left(4),
+		Command::ScrollRight => app.code_scroll_right(4),
+		Command::NextComment => app.next_comment(),
+		Command::PreviousComment => app.previous_comment(),
+		Command::ToggleImages => app.images.toggle(),
+		Command::OpenViewer => app.open_viewer(v),
+		Command::OpenLinks => app.open_link_picker(v),
+		Command::ShrinkList | Command::GrowList | Command::ShrinkPost | Command::GrowPost => {
+			app.resize_pane(command)
+		}
+		Command::Select if app.index == 2 => {
+			app.remember(v, last_post_id);
+			app.viewing_hidden = false;
+			*v = app.load_feed(false, last_post_id);
+			app.items.state.select(Some(0));
+		}
+		Command::DeleteChar => app.delete_from_input(),
+		Command::PageDown | Command::PageUp | Command::HalfPageDown | Command::HalfPageUp => {
+			match post_tab {
+				true => app.page_pane(command),
+				false => app.page_list(command, v, last_post_id),
+			}
+		}
+		Command::CopyPermalink
+		| Command::CopyUrl
+		| Command::CopyTitle
+		| Command::CopyText
+		| Command::CopyComment
+		| Command::CopyCommentLink => app.copy(command, v),
+		Command::Find => app.open_find(),
+		Command::FindNext => app.find_next(true),
+		Command::FindPrevious => app.find_next(false),
+		Command::NextFeed => app.switch_feed((app.feed + 1) % app.feeds.len(), v, last_post_id),
+		Command::PreviousFeed => app.switch_feed(
+			(app.feed + app.feeds.len() - 1) % app.feeds.len(),
+			v,
+			last_post_id,
+		),
+		Command::NewFeed => {
+			app.command_line.open_with("tab ");
+			app.command_line_open = true;
+		}
+		Command::CloseFeed => app.close_feed(v, last_post_id),
+		Command::MoveFeedLeft => app.move_feed(app.feed.saturating_sub(1)),
+		Command::MoveFeedRight => app.move_feed(app.feed + 1),
+		_ => {}
+	}
+	Ok(())
+}
+
+/// Runs a command from the keymap, `count` times if it takes a count.
+/// Returns whether rusddit should quit.
+fn run_command<B: Backend>(
+	terminal: &mut Terminal<B>,
+	app: &mut App,
+	context: Context,
+	command: Command,
+	count: usize,
+	v: &mut Vec<serde_json::Value>,
+	last_post_id: &mut String,
+) -> io::Result<bool> {
+	let count = match command.takes_count() {
+		true => count,
+		false => 1,
+	};
+	for _ in 0..count {
+		match (context, command) {
+			(_, Command::Quit) if app.confirm_quit => app.dialog = Some(Dialog::Quit),
+			(_, Command::Quit) => return Ok(true),
+			(Context::CommandLine, Command::Select) => {
+				app.command_line_open = false;
+				let line = app.command_line.submit();
+				if run_line(terminal, app, &line, v, last_post_id)? {
+					return Ok(true);
+				}
+			}
+			(Context::CommandLine, _) => app.command_line_command(command),
+			(Context::Find, _) => app.find_command(command),
+			(_, Command::OpenCommandLine) => {
+				app.command_line.open();
+				app.command_line_open = true;
+			}
+			(Context::Help, _) => app.help_command(command),
+			(_, Command::Help) => app.help = Some(Help::new(&app.keymap, context)),
+			(Context::Viewer, _) => app.viewer_command(command, v),
+			(Context::Links, _) => run_link_command(terminal, app, command, v, last_post_id)?,
+			(_, Command::NextTab) => app.next_tab(v),
+			(_, Command::PreviousTab) => app.previous_tab(v),
+			(_, Command::SwitchAccount) => app.open_accounts(),
+			(_, Command::Back) => app.go_back(v, last_post_id),
+			(_, Command::Forward) => app.go_forward(v, last_post_id),
+			(Context::Mod, _) => app.mod_command(command),
+			_ => run_tab_command(terminal, app, command, v, last_post_id)?,
+		}
+	}
+	Ok(false)
+}
+
+/// How to call the commands of the command line that take arguments.
+fn usage(name: &str) -> Option<&'static str> {
+	Some(match name {
+		"r" | "subreddit" => "r <subreddit>",
+		"u" | "user" => "u <user>",
+		"sort" => "sort <sort> [time]",
+		"search" => "search <query>",
+		"open" => "open <url>",
+		"set" => "set <option> <value>",
+		"login" => "login <reddit_session cookie>",
+		"account" => "account [name]",
+		"map" => "map <context> <keys> <command>",
+		"tab" | "tabnew" => "tab <feed>",
+		"tabmove" => "tabmove <position>",
+		_ => return None,
+	})
+}
+
+/// Runs a line of the command line or the rc file. Besides its own commands
+/// every keymap command can be run by name, with an optional count.
+/// Returns whether rusddit should quit.
+fn run_line<B: Backend>(
+	terminal: &mut Terminal<B>,
+	app: &mut App,
+	line: &str,
+	v: &mut Vec<serde_json::Value>,
+	last_post_id: &mut String,
+) -> io::Result<bool> {
+	let words: Vec<&str> = line
+		.trim()
+		.trim_start_matches(':')
+		.split_whitespace()
+		.collect();
+	let (name, args) = match words.split_first() {
+		Some(x) => x,
+		None => return Ok(false),
+	};
+	let result = match (*name, args) {
+		("q" | "quit", []) => return Ok(true),
+		("r" | "subreddit", [subreddit]) => {
+			let target = Target::Subreddit(subreddit.trim_start_matches("r/").to_string());
+			app.follow(target, v, last_post_id);
+			Ok(())
+		}
+		("u" | "user", [user]) => {
+			let target = Target::User(user.trim_start_matches("u/").to_string());
+			app.follow(target, v, last_post_id);
+			Ok(())
+		}
+		("sort", [sort]) | ("sort", [sort, _]) if !SORTS.contains(sort) => Err(format!(
+			"{}{}{}{}",
+			"Unknown sort: ",
+			sort,
+			", choose one of ",
+			SORTS.join(", ")
+		)),
+		("sort", [_, time]) if !TIMES.contains(time) => Err(format!(
+			"{}{}{}{}",
+			"Unknown time: ",
+			time,
+			", choose one of ",
+			TIMES.join(", ")
+		)),
+		("sort", [sort]) | ("sort", [sort, _]) => {
+			let time = args.get(1).copied().unwrap_or_default();
+			if let Some(command) = Command::from_name(&format!("{}{}", "sort_", sort)) {
+				app.sort(command, time, v, last_post_id);
+			}
+			Ok(())
+		}
+		("search", [_, ..]) => {
+			app.show_feed(format!("{}{}", "search/", args.join(" ")), v, last_post_id);
+			Ok(())
+		}
+		("open", [url]) => {
+			activate_link(terminal, app, url, v, last_post_id)?;
+			Ok(())
+		}
+		("set", [option, value]) => app.set(option, value),
+		("tab" | "tabnew", [_, ..]) => {
+			app.open_feed(&feeds::feed_path(&args.join(" ")), v, last_post_id);
+			Ok(())
+		}
+		("tabclose", []) => {
+			app.close_feed(v, last_post_id);
+			Ok(())
+		}
+		("tabmove", [position]) => match position.parse::<usize>() {
+			Ok(x) if x > 0 => {
+				app.move_feed(x - 1);
+				Ok(())
+			}
+			_ => Err(format!("{}{}", "Not a position: ", position)),
+		},
+		("login", [cookie]) => app.login(cookie),
+		("account", []) => {
+			app.open_accounts();
+			Ok(())
+		}
+		("account", [name]) => app.switch_account(name, v, last_post_id),
+		("map", [context, keys, command]) => match keymap::Context::from_name(context) {
+			Some(context) => app.keymap.bind(context, keys, command),
+			None => Err(format!("{}{}", "Unknown key context: ", context)),
+		},
+		(name, _) if usage(name).is_some() => {
+			Err(format!("{}{}", "Usage: ", usage(name).unwrap_or_default()))
+		}
+		(name, []) | (name, [_]) if Command::from_name(name).is_some() => {
+			let count = match args.first().map(|x| x.parse::<usize>()) {
+				Some(Ok(x)) => Ok(x),
+				Some(Err(_)) => Err(format!("{}{}", "Not a count: ", args[0])),
+				None => Ok(1),
+			};
+			match (Command::from_name(name), count) {
+				(Some(command), Ok(count)) => {
+					let context = app.key_context();
+					return run_command(terminal, app, context, command, count, v, last_post_id);
+				}
+				(_, Err(err)) => Err(err),
+				(None, _) => Ok(()),
+			}
+		}
+		(name, _) => Err(format!("{}{}", "Unknown command: ", name)),
+	};
+	if let Err(err) = result {
+		app.dialog = Some(Dialog::Message(err));
+	}
+	Ok(false)
+}
+
+/// Draws the screen with the loading indicator before a command that may wait
+/// on reddit, since requests block until they are done.
+fn show_loading<B: Backend>(
+	terminal: &mut Terminal<B>,
+	app: &mut App,
+	v: &[serde_json::Value],
+) -> io::Result<()> {
+	app.status.loading = true;
+	app.images.begin_frame();
+	terminal.draw(|f| ui(f, app, v))?;
+	Ok(())
+}
+
+pub fn run_app<B: Backend>(
+	terminal: &mut Terminal<B>,
+	mut app: App,
+	mut v: Vec<serde_json::Value>,
+	last_post_id: &mut String,
+) -> io::Result<()> {
+	// Errors of the rc file are shown together with the startup errors
+	let mut errors = match app.dialog.take() {
+		Some(Dialog::Message(x)) => vec![x],
+		_ => vec![],
+	};
+	for (number, line) in command_line::read_rc() {
+		if run_line(terminal, &mut app, &line, &mut v, last_post_id)? {
+			return Ok(());
+		}
+		if let Some(Dialog::Message(err)) = &app.dialog {
+			errors.push(format!("{}{}{}{}", "rusddit.rc line ", number, ": ", err));
+			app.dialog = None;
+		}
+	}
+	if !errors.is_empty() {
+		app.dialog = Some(Dialog::Message(errors.join("\n")));
+	}
+	loop {
+		if app.index == 3 && app.moderation.is_none() {
+			app.load_moderation();
+		}
+		app.images.begin_frame();
+		terminal.draw(|f| ui(f, &mut app, &v))?;
+		if app.images.needs_clear() {
+			app.images.clear()?;
+			terminal.clear()?;
+			app.images.begin_frame();
+			terminal.draw(|f| ui(f, &mut app, &v))?;
+		}
+		if app.images.changed() {
+			app.images.present(app.credentials.cookie.expose());
+		}
+		// Wake up to redraw once the toast is over
+		if let Some(timeout) = app.status.toast_timeout() {
+			if !event::poll(timeout)? {
+				continue;
+			}
+		}
+		match event::read()? {
+			Event::Mouse(mouse) => app.handle_mouse(mouse, &mut v, last_post_id),
+			Event::Key(key) => {
+				if app.dialog.is_some() {
+					let quitting = matches!(app.dialog, Some(Dialog::Quit));
+					if matches!(key.code, KeyCode::Enter | KeyCode::Char('y')) && !quitting {
+						show_loading(terminal, &mut app, &v)?;
 					}
-					KeyCode::Enter => {
-						v = utils::get_posts(
-							app.input.clone(),
-							false,
-							app.sort_by.clone(),
-							&mut last_post_id,
-							reddit_cookie,
-						);
-						app.items.state.select(Some(0));
-						// app.index = 0;
+					if app.handle_dialog_key(key.code, &mut v, last_post_id) {
+						return Ok(());
 					}
-					KeyCode::Left => {
-						app.previous();
-						app.update_comments(v.clone(), app.items.state.selected().unwrap());
+					app.status.loading = false;
+					continue;
+				}
+				let context = app.key_context();
+				for resolved in app.keymap.feed(&mut app.keys, context, key.into()) {
+					match resolved {
+						Resolved::Command(command, count) => {
+							if command.loads() {
+								show_loading(terminal, &mut app, &v)?;
+							}
+							let quit = run_command(
+								terminal,
+								&mut app,
+								context,
+								command,
+								count,
+								&mut v,
+								last_post_id,
+							)?;
+							app.status.loading = false;
+							if quit {
+								return Ok(());
+							}
+						}
+						Resolved::Unbound(key) => match (context, key.char()) {
+							(Context::Search, Some(c)) => app.append_input(c),
+							(Context::CommandLine, Some(c)) => app.command_line.type_char(c),
+							(Context::Find, Some(c)) => {
+								if let Some(find) = &mut app.find {
+									find.type_char(c);
+								}
+							}
+							(Context::Help, Some(c)) => {
+								if let Some(help) = &mut app.help {
+									help.type_char(c);
+								}
+							}
+							(Context::Links, Some(c)) => {
+								type_link_hint(terminal, &mut app, c, &mut v, last_post_id)?
+							}
+							_ => {}
+						},
+						Resolved::Pending => {}
 					}
-					KeyCode::Right => app.next(),
-					_ => {}
 				}
 			}
+			_ => {}
 		}
 	}
 }
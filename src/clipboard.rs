@@ -0,0 +1,87 @@
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+pub const DEFAULT_BACKEND: &str = "auto";
+
+/// Values of the `clipboard` option.
+pub const BACKENDS: [&str; 3] = ["auto", "osc52", "native"];
+
+/// Programs that set the system clipboard from their input, tried in order.
+/// The first one whose display is there is used.
+const NATIVE: [(&str, &[&str]); 5] = [
+	("pbcopy", &[]),
+	("wl-copy", &[]),
+	("xclip", &["-selection", "clipboard"]),
+	("xsel", &["--clipboard", "--input"]),
+	("clip.exe", &[]),
+];
+
+/// Copies `text` with `backend`. OSC 52 asks the terminal to set the
+/// clipboard, which works over SSH and in tmux. `native` runs a clipboard
+/// program instead, and `auto` does that on a local desktop and falls back
+/// to OSC 52 when there is none.
+pub fn copy(text: &str, backend: &str) -> Result<(), String> {
+	match backend {
+		"osc52" => osc52(text).map_err(|err| err.to_string()),
+		"native" => native(text),
+		_ if env::var_os("SSH_TTY").is_some() => osc52(text).map_err(|err| err.to_string()),
+		_ => native(text).or_else(|_| osc52(text).map_err(|err| err.to_string())),
+	}
+}
+
+/// Writes the OSC 52 sequence, wrapped so tmux and screen pass it on to the
+/// terminal they run in.
+fn osc52(text: &str) -> io::Result<()> {
+	let sequence = format!("{}{}{}", "\x1b]52;c;", base64::encode(text), "\x07");
+	let sequence = match (env::var_os("TMUX"), env::var_os("STY")) {
+		(Some(_), _) => format!(
+			"{}{}{}",
+			"\x1bPtmux;",
+			sequence.replace('\x1b', "\x1b\x1b"),
+			"\x1b\\"
+		),
+		(None, Some(_)) => format!("{}{}{}", "\x1bP", sequence, "\x1b\\"),
+		(None, None) => sequence,
+	};
+	let mut stdout = io::stdout();
+	write!(stdout, "{}", sequence)?;
+	stdout.flush()
+}
+
+fn native(text: &str) -> Result<(), String> {
+	let (program, args) = NATIVE
+		.iter()
+		.find(|x| available(x.0))
+		.ok_or_else(|| "No clipboard program found, install wl-copy, xclip or xsel".to_string())?;
+	let mut child = Command::new(program)
+		.args(args.iter())
+		.stdin(Stdio::piped())
+		.stdout(Stdio::null())
+		.stderr(Stdio::null())
+		.spawn()
+		.map_err(|err| format!("{}{}{}{}", "Couldn't start ", program, ": ", err))?;
+	if let Some(mut stdin) = child.stdin.take() {
+		stdin
+			.write_all(text.as_bytes())
+			.map_err(|err| format!("{}{}{}{}", "Couldn't write to ", program, ": ", err))?;
+	}
+	let status = child.wait().map_err(|err| err.to_string())?;
+	match status.success() {
+		true => Ok(()),
+		false => Err(format!("{}{}", program, " failed")),
+	}
+}
+
+/// Whether `program` is on the PATH and has a display to talk to.
+fn available(program: &str) -> bool {
+	let display = match program {
+		"pbcopy" => cfg!(target_os = "macos"),
+		"wl-copy" => env::var_os("WAYLAND_DISPLAY").is_some(),
+		"xclip" | "xsel" => env::var_os("DISPLAY").is_some(),
+		_ => true,
+	};
+	display
+		&& env::var_os("PATH")
+			.is_some_and(|paths| env::split_paths(&paths).any(|x| x.join(program).is_file()))
+}
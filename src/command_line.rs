@@ -0,0 +1,244 @@
+use tui::{
+	backend::Backend,
+	layout::Rect,
+	style::Style,
+	text::{Span, Spans},
+	widgets::Paragraph,
+	Frame,
+};
+
+use crate::clipboard::BACKENDS;
+use crate::keymap::{self, CONTEXTS, PRESETS};
+use crate::theme::{Theme, THEMES};
+use crate::user_config;
+
+/// Commands of the command line besides the keymap's commands, which can be
+/// run by name as well.
+pub const LINE_COMMANDS: [&str; 17] = [
+	"r",
+	"subreddit",
+	"u",
+	"user",
+	"sort",
+	"search",
+	"open",
+	"set",
+	"login",
+	"account",
+	"map",
+	"tab",
+	"tabnew",
+	"tabclose",
+	"tabmove",
+	"q",
+	"quit",
+];
+
+/// Options of `:set`.
+pub const OPTIONS: [&str; 6] = [
+	"theme",
+	"keymap",
+	"thumbnails",
+	"images",
+	"mouse",
+	"clipboard",
+];
+
+pub const SORTS: [&str; 6] = ["hot", "new", "top", "rising", "best", "controversial"];
+
+/// Time ranges of the top and controversial sorts.
+pub const TIMES: [&str; 6] = ["hour", "day", "week", "month", "year", "all"];
+
+const SWITCHES: [&str; 2] = ["on", "off"];
+
+/// How many lines the history keeps.
+const HISTORY_SIZE: usize = 100;
+
+/// Lines in this file of the config directory are run on startup as if typed
+/// into the command line.
+const RC_FILE: &str = "rusddit.rc";
+
+/// Commands whose argument is a secret. It is hidden while typed and the
+/// line is left out of the history.
+const SECRET_COMMANDS: [&str; 1] = ["login"];
+
+/// The `:` prompt at the bottom of the screen.
+#[derive(Default)]
+pub struct CommandLine {
+	pub input: String,
+	history: Vec<String>,
+	/// Position in the history while going through it with up and down.
+	history_index: Option<usize>,
+	/// Completions of the last word and which one is shown, while tab is
+	/// pressed repeatedly.
+	completions: Option<(Vec<String>, usize)>,
+}
+
+impl CommandLine {
+	/// Opens the prompt empty, keeping the history of earlier lines.
+	pub fn open(&mut self) {
+		self.open_with("");
+	}
+
+	/// Opens the prompt with `text` already typed.
+	pub fn open_with(&mut self, text: &str) {
+		self.input = text.to_string();
+		self.history_index = None;
+		self.completions = None;
+	}
+
+	pub fn type_char(&mut self, c: char) {
+		self.input.push(c);
+		self.completions = None;
+	}
+
+	pub fn delete_char(&mut self) {
+		self.input.pop();
+		self.completions = None;
+	}
+
+	/// Takes the typed line, remembering it in the history.
+	pub fn submit(&mut self) -> String {
+		let line = std::mem::take(&mut self.input);
+		if !line.trim().is_empty() && self.history.last() != Some(&line) && masked(&line) == line {
+			self.history.push(line.clone());
+			if self.history.len() > HISTORY_SIZE {
+				self.history.remove(0);
+			}
+		}
+		line
+	}
+
+	pub fn history_previous(&mut self) {
+		let i = match self.history_index {
+			Some(i) => i.saturating_sub(1),
+			None if self.history.is_empty() => return,
+			None => self.history.len() - 1,
+		};
+		self.history_index = Some(i);
+		self.input = self.history[i].clone();
+		self.completions = None;
+	}
+
+	pub fn history_next(&mut self) {
+		let i = match self.history_index {
+			Some(i) => i + 1,
+			None => return,
+		};
+		self.input = match self.history.get(i) {
+			Some(x) => {
+				self.history_index = Some(i);
+				x.clone()
+			}
+			None => {
+				self.history_index = None;
+				String::new()
+			}
+		};
+		self.completions = None;
+	}
+
+	/// Completes the last word. Pressing tab again goes through the other
+	/// completions.
+	pub fn complete(&mut self) {
+		let (completions, i) = match self.completions.take() {
+			Some((completions, i)) => {
+				let i = (i + 1) % completions.len();
+				(completions, i)
+			}
+			None => {
+				let completions = completions(&self.input);
+				if completions.is_empty() {
+					return;
+				}
+				(completions, 0)
+			}
+		};
+		let start = self.input.rfind(' ').map_or(0, |x| x + 1);
+		self.input = format!("{}{}", &self.input[..start], completions[i]);
+		self.completions = Some((completions, i));
+	}
+}
+
+/// Words that can follow `words`, the words before the one being typed.
+fn candidates(words: &[&str]) -> Vec<&'static str> {
+	match words {
+		[] => {
+			let mut names = LINE_COMMANDS.to_vec();
+			names.extend(keymap::command_names());
+			names
+		}
+		["set"] => OPTIONS.to_vec(),
+		["set", "theme"] => THEMES.to_vec(),
+		["set", "keymap"] => PRESETS.to_vec(),
+		["set", "clipboard"] => BACKENDS.to_vec(),
+		["set", "thumbnails" | "images" | "mouse"] => SWITCHES.to_vec(),
+		["sort"] => SORTS.to_vec(),
+		["sort", "top" | "controversial"] => TIMES.to_vec(),
+		["map"] => CONTEXTS.iter().map(|x| x.0).collect(),
+		["map", _, _] => keymap::command_names(),
+		_ => vec![],
+	}
+}
+
+/// Completions of the last word of `input`, sorted.
+fn completions(input: &str) -> Vec<String> {
+	let mut words: Vec<&str> = input.split(' ').collect();
+	let last = words.pop().unwrap_or_default();
+	words.retain(|x| !x.is_empty());
+	let mut found: Vec<String> = candidates(&words)
+		.into_iter()
+		.filter(|x| x.starts_with(last))
+		.map(|x| x.to_string())
+		.collect();
+	found.sort();
+	found.dedup();
+	found
+}
+
+/// The lines of the rc file, without blank lines and `#` comments.
+pub fn read_rc() -> Vec<(usize, String)> {
+	std::fs::read_to_string(user_config::config_dir().join(RC_FILE))
+		.unwrap_or_default()
+		.lines()
+		.enumerate()
+		.map(|(i, x)| (i + 1, x.trim().to_string()))
+		.filter(|x| !x.1.is_empty() && !x.1.starts_with('#'))
+		.collect()
+}
+
+/// `input` with the argument of a secret command replaced by stars.
+fn masked(input: &str) -> String {
+	let command = input.trim_start().trim_start_matches(':');
+	match command.split_once(' ') {
+		Some((name, secret)) if SECRET_COMMANDS.contains(&name) => format!(
+			"{}{}",
+			&input[..input.len() - secret.len()],
+			"*".repeat(secret.chars().count())
+		),
+		_ => input.to_string(),
+	}
+}
+
+pub fn draw_command_line<B>(f: &mut Frame<B>, line: &CommandLine, area: Rect, theme: &Theme)
+where
+	B: Backend,
+{
+	let mut spans = vec![Span::raw(":"), Span::raw(masked(&line.input))];
+	if let Some((completions, i)) = &line.completions {
+		if completions.len() > 1 {
+			spans.push(Span::styled("  ", Style::default()));
+			for (j, completion) in completions.iter().enumerate() {
+				let style = match j == *i {
+					true => theme.highlight(),
+					false => Style::default().fg(theme.dim),
+				};
+				spans.push(Span::styled(completion.clone(), style));
+				spans.push(Span::raw(" "));
+			}
+		}
+	}
+	f.render_widget(Paragraph::new(Spans::from(spans)).style(theme.base()), area);
+	let cursor = area.x + 1 + line.input.chars().count() as u16;
+	f.set_cursor(cursor.min(area.right().saturating_sub(1)), area.y);
+}
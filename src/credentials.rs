@@ -0,0 +1,578 @@
+use crossterm::{
+	event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
+	terminal::{disable_raw_mode, enable_raw_mode},
+};
+use openssl::{
+	hash::MessageDigest,
+	pkcs5::pbkdf2_hmac,
+	rand::rand_bytes,
+	symm::{decrypt_aead, encrypt_aead, Cipher},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+	collections::BTreeMap,
+	env, fmt, fs,
+	io::{self, BufRead, Write},
+	path::PathBuf,
+};
+
+use crate::user_config;
+
+/// Cookie to run with instead of the saved one. It isn't saved.
+pub const COOKIE_VAR: &str = "RUSDDIT_COOKIE";
+
+/// Passphrase of the credentials file, asked for on startup without it.
+pub const PASSPHRASE_VAR: &str = "RUSDDIT_PASSPHRASE";
+
+const FILE: &str = "credentials.toml";
+
+const SALT_LENGTH: usize = 16;
+const NONCE_LENGTH: usize = 12;
+const TAG_LENGTH: usize = 16;
+const ITERATIONS: usize = 600_000;
+
+/// Text that mustn't end up on screen or in logs, like the session cookie.
+/// Its `Debug` output leaves the text out.
+#[derive(Clone, Default, PartialEq)]
+pub struct Secret(String);
+
+impl Secret {
+	pub fn new(text: &str) -> Secret {
+		Secret(text.trim().to_string())
+	}
+
+	pub fn expose(&self) -> &str {
+		&self.0
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.0.is_empty()
+	}
+}
+
+impl fmt::Debug for Secret {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.write_str("Secret(redacted)")
+	}
+}
+
+/// A cookie in `credentials.toml`, in plain text or encrypted with a passphrase.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+struct Entry {
+	#[serde(skip_serializing_if = "String::is_empty")]
+	cookie: String,
+	/// Salt, nonce, tag and encrypted cookie in base64.
+	#[serde(skip_serializing_if = "String::is_empty")]
+	encrypted: String,
+}
+
+/// `credentials.toml`, with the cookie of the default account at the top and
+/// those of named accounts in `[accounts]`.
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default, deny_unknown_fields)]
+struct Stored {
+	#[serde(skip_serializing_if = "String::is_empty")]
+	cookie: String,
+	#[serde(skip_serializing_if = "String::is_empty")]
+	encrypted: String,
+	#[serde(skip_serializing_if = "BTreeMap::is_empty")]
+	accounts: BTreeMap<String, Entry>,
+}
+
+impl Stored {
+	fn entry(&self, account: &str) -> Entry {
+		match account {
+			"" => Entry {
+				cookie: self.cookie.clone(),
+				encrypted: self.encrypted.clone(),
+			},
+			x => self.accounts.get(x).cloned().unwrap_or_default(),
+		}
+	}
+
+	fn set(&mut self, account: &str, entry: Entry) {
+		match account {
+			"" => {
+				self.cookie = entry.cookie;
+				self.encrypted = entry.encrypted;
+			}
+			x if entry.cookie.is_empty() && entry.encrypted.is_empty() => {
+				self.accounts.remove(x);
+			}
+			x => {
+				self.accounts.insert(x.to_string(), entry);
+			}
+		}
+	}
+}
+
+/// The reddit_session cookie rusddit runs with, and how to save a new one.
+#[derive(Default)]
+pub struct Credentials {
+	/// Account the cookie is of, empty for the default one.
+	pub account: String,
+	pub cookie: Secret,
+	/// Passphrase the cookie was unlocked with. A new cookie is encrypted
+	/// with it too.
+	passphrase: Option<Secret>,
+	/// Why the cookie mustn't be saved, when the file couldn't be read or the
+	/// cookie in it wasn't unlocked.
+	locked: Option<String>,
+}
+
+impl Credentials {
+	/// The cookie of `RUSDDIT_COOKIE`, or else the one of `account` in the
+	/// credentials file, asking for its passphrase when it is encrypted.
+	/// Without either the session is anonymous; when the file can't be read
+	/// that is said too.
+	pub fn load(account: &str) -> (Credentials, Option<String>) {
+		let cookie = match env::var(COOKIE_VAR) {
+			Ok(x) => Secret::new(&x),
+			Err(_) => {
+				let text = format!(
+					"{}{}{}",
+					"Passphrase of the reddit cookie of ",
+					user_config::account_name(account),
+					": "
+				);
+				return match Credentials::from_file(account, || prompt(&text)) {
+					(credentials, Some(err)) => (
+						credentials,
+						Some(format!(
+							"{}{}",
+							err, ", browsing without logging in until rusddit is restarted"
+						)),
+					),
+					x => x,
+				};
+			}
+		};
+		// The file isn't unlocked, so an encrypted cookie in it is kept as it is
+		let locked = match read() {
+			Ok(x) if x.entry(account).encrypted.is_empty() => None,
+			Ok(_) => Some(locked(account)),
+			Err(err) => Some(err),
+		};
+		(
+			Credentials {
+				account: account.to_string(),
+				cookie,
+				locked,
+				..Credentials::default()
+			},
+			None,
+		)
+	}
+
+	/// The cookie of `account` in the file. `passphrase` is asked for when it
+	/// is encrypted.
+	fn from_file(
+		account: &str,
+		passphrase: impl FnOnce() -> Result<Secret, String>,
+	) -> (Credentials, Option<String>) {
+		let mut credentials = Credentials {
+			account: account.to_string(),
+			..Credentials::default()
+		};
+		let entry = match read() {
+			Ok(x) => x.entry(account),
+			Err(err) => {
+				credentials.locked = Some(err.clone());
+				return (credentials, Some(err));
+			}
+		};
+		if entry.encrypted.is_empty() {
+			credentials.cookie = Secret::new(&entry.cookie);
+			return (credentials, None);
+		}
+		match passphrase().and_then(|x| Ok((decrypt(&entry.encrypted, &x)?, x))) {
+			Ok((cookie, passphrase)) => {
+				credentials.cookie = cookie;
+				credentials.passphrase = Some(passphrase);
+				(credentials, None)
+			}
+			Err(err) => {
+				credentials.locked = Some(locked(account));
+				(credentials, Some(err))
+			}
+		}
+	}
+
+	/// The credentials of another account, for switching to it while rusddit
+	/// runs. An encrypted cookie is tried with the passphrase this one was
+	/// unlocked with, or `RUSDDIT_PASSPHRASE`, as there is nowhere to type one.
+	pub fn switch(&self, account: &str) -> Result<Credentials, String> {
+		let passphrase = self
+			.passphrase
+			.clone()
+			.or_else(|| env::var(PASSPHRASE_VAR).ok().map(Secret))
+			.ok_or_else(|| locked(account));
+		match Credentials::from_file(account, || passphrase) {
+			(credentials, None) => Ok(credentials),
+			(_, Some(_)) if self.passphrase.is_some() => Err(format!(
+				"{}{}",
+				"Its cookie has another passphrase. ",
+				locked(account)
+			)),
+			(_, Some(err)) => Err(err),
+		}
+	}
+
+	pub fn is_encrypted(&self) -> bool {
+		self.passphrase.is_some()
+	}
+
+	/// Makes `cookie` the one rusddit runs with and saves it, encrypted
+	/// when the old one was.
+	pub fn save(&mut self, cookie: Secret) -> Result<(), String> {
+		if let Some(reason) = &self.locked {
+			return Err(reason.clone());
+		}
+		let entry = match &self.passphrase {
+			Some(passphrase) => Entry {
+				encrypted: encrypt(&cookie, passphrase)?,
+				..Entry::default()
+			},
+			None => Entry {
+				cookie: cookie.expose().to_string(),
+				..Entry::default()
+			},
+		};
+		let mut stored = read()?;
+		stored.set(&self.account, entry);
+		write(&stored)?;
+		self.cookie = cookie;
+		Ok(())
+	}
+
+	/// Saves the cookie encrypted with `passphrase`, or in plain text without one.
+	pub fn set_passphrase(&mut self, passphrase: Option<Secret>) -> Result<(), String> {
+		self.passphrase = passphrase;
+		self.save(self.cookie.clone())
+	}
+}
+
+/// Why an encrypted cookie that wasn't unlocked can't be used or replaced.
+fn locked(account: &str) -> String {
+	let name = user_config::account_name(account);
+	format!(
+		"{}{}{}{}{}",
+		"The cookie of ",
+		name,
+		" is encrypted, start rusddit with --account ",
+		name,
+		" to enter its passphrase"
+	)
+}
+
+pub fn path() -> PathBuf {
+	user_config::config_dir().join(FILE)
+}
+
+/// Named accounts that have a cookie saved.
+pub fn accounts() -> Vec<String> {
+	read()
+		.map(|x| x.accounts.into_keys().collect())
+		.unwrap_or_default()
+}
+
+fn read() -> Result<Stored, String> {
+	let path = path();
+	let text = match fs::read_to_string(&path) {
+		Ok(x) => x,
+		Err(_) => return Ok(Stored::default()),
+	};
+	restrict(&path);
+	toml::from_str(&text).map_err(|err| {
+		format!(
+			"{}{}{}",
+			path.display(),
+			" isn't valid: ",
+			err.message().trim()
+		)
+	})
+}
+
+fn write(stored: &Stored) -> Result<(), String> {
+	let text = format!(
+		"{}{}",
+		"# reddit_session cookies of rusddit, only readable by you. Change them with\n# rusddit -c - or :login.\n",
+		toml::to_string(stored).map_err(|err| err.to_string())?
+	);
+	let path = path();
+	if let Some(dir) = path.parent() {
+		fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+	}
+	let mut options = fs::OpenOptions::new();
+	options.write(true).create(true).truncate(true);
+	#[cfg(unix)]
+	std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+	// The file may have been created before with more permissions
+	restrict(&path);
+	options
+		.open(&path)
+		.and_then(|mut file| file.write_all(text.as_bytes()))
+		.map_err(|err| format!("{}{}{}{}", "Couldn't write ", path.display(), ": ", err))
+}
+
+/// Makes the file at `path` readable by its owner only.
+fn restrict(path: &PathBuf) {
+	#[cfg(unix)]
+	{
+		use std::os::unix::fs::PermissionsExt;
+		if let Ok(metadata) = fs::metadata(path) {
+			if metadata.permissions().mode() & 0o077 != 0 {
+				let _ = fs::set_permissions(path, fs::Permissions::from_mode(0o600));
+			}
+		}
+	}
+}
+
+/// Removes the saved cookie of `account`.
+pub fn forget(account: &str) -> Result<(), String> {
+	let mut stored = read()?;
+	stored.set(account, Entry::default());
+	write(&stored)
+}
+
+fn key(passphrase: &Secret, salt: &[u8]) -> Result<[u8; 32], String> {
+	let mut key = [0; 32];
+	pbkdf2_hmac(
+		passphrase.expose().as_bytes(),
+		salt,
+		ITERATIONS,
+		MessageDigest::sha256(),
+		&mut key,
+	)
+	.map_err(|err| err.to_string())?;
+	Ok(key)
+}
+
+/// `cookie` encrypted with AES-256-GCM under a key derived from `passphrase`.
+fn encrypt(cookie: &Secret, passphrase: &Secret) -> Result<String, String> {
+	let mut salt = [0; SALT_LENGTH];
+	let mut nonce = [0; NONCE_LENGTH];
+	let mut tag = [0; TAG_LENGTH];
+	rand_bytes(&mut salt).map_err(|err| err.to_string())?;
+	rand_bytes(&mut nonce).map_err(|err| err.to_string())?;
+	let encrypted = encrypt_aead(
+		Cipher::aes_256_gcm(),
+		&key(passphrase, &salt)?,
+		Some(&nonce),
+		&[],
+		cookie.expose().as_bytes(),
+		&mut tag,
+	)
+	.map_err(|err| err.to_string())?;
+	Ok(base64::encode(
+		[&salt[..], &nonce, &tag, &encrypted].concat(),
+	))
+}
+
+fn decrypt(encrypted: &str, passphrase: &Secret) -> Result<Secret, String> {
+	let invalid = || format!("{}{}", path().display(), " isn't valid");
+	let bytes = base64::decode(encrypted).map_err(|_| invalid())?;
+	if bytes.len() < SALT_LENGTH + NONCE_LENGTH + TAG_LENGTH {
+		return Err(invalid());
+	}
+	let (salt, rest) = bytes.split_at(SALT_LENGTH);
+	let (nonce, rest) = rest.split_at(NONCE_LENGTH);
+	let (tag, encrypted) = rest.split_at(TAG_LENGTH);
+	let cookie = decrypt_aead(
+		Cipher::aes_256_gcm(),
+		&key(passphrase, salt)?,
+		Some(nonce),
+		&[],
+		encrypted,
+		tag,
+	)
+	.map_err(|_| "Wrong passphrase for the reddit cookie".to_string())?;
+	String::from_utf8(cookie)
+		.map(|x| Secret::new(&x))
+		.map_err(|_| invalid())
+}
+
+/// The first line of standard input, for `rusddit -c -`.
+pub fn read_stdin() -> Result<Secret, String> {
+	let mut line = String::new();
+	io::stdin()
+		.lock()
+		.read_line(&mut line)
+		.map_err(|err| err.to_string())?;
+	match line.trim().is_empty() {
+		true => Err("No cookie on standard input".to_string()),
+		false => Ok(Secret::new(&line)),
+	}
+}
+
+/// The passphrase of `RUSDDIT_PASSPHRASE`, or else one typed into the
+/// terminal without showing it.
+pub fn prompt(text: &str) -> Result<Secret, String> {
+	if let Ok(passphrase) = env::var(PASSPHRASE_VAR) {
+		return Ok(Secret(passphrase));
+	}
+	print!("{}", text);
+	io::stdout().flush().map_err(|err| err.to_string())?;
+	enable_raw_mode().map_err(|err| err.to_string())?;
+	let mut passphrase = String::new();
+	let result = loop {
+		match event::read() {
+			Ok(Event::Key(KeyEvent {
+				code: KeyCode::Enter,
+				..
+			})) => break Ok(()),
+			Ok(Event::Key(KeyEvent {
+				code: KeyCode::Char('c'),
+				modifiers: KeyModifiers::CONTROL,
+			})) => break Err("No passphrase entered".to_string()),
+			Ok(Event::Key(KeyEvent {
+				code: KeyCode::Backspace,
+				..
+			})) => {
+				passphrase.pop();
+			}
+			Ok(Event::Key(KeyEvent {
+				code: KeyCode::Char(x),
+				..
+			})) => passphrase.push(x),
+			Ok(_) => {}
+			Err(err) => break Err(err.to_string()),
+		}
+	};
+	let _ = disable_raw_mode();
+	println!();
+	result.map(|_| Secret(passphrase))
+}
+
+/// Saves the cookie of an earlier version's config as the default account's,
+/// unless one was saved since.
+pub fn import(cookie: &str) -> Result<(), String> {
+	let cookie = Secret::new(cookie);
+	let mut stored = read()?;
+	let entry = stored.entry("");
+	if cookie.is_empty() || !entry.cookie.is_empty() || !entry.encrypted.is_empty() {
+		return Ok(());
+	}
+	stored.set(
+		"",
+		Entry {
+			cookie: cookie.expose().to_string(),
+			..Entry::default()
+		},
+	);
+	write(&stored)
+}
+
+/// Moves a cookie left in `config.toml` by earlier versions to the
+/// credentials file. Returns what happened, if anything did.
+pub fn migrate(config: &mut user_config::UserConfig) -> Option<String> {
+	if config.cookie.is_empty() {
+		return None;
+	}
+	let cookie = std::mem::take(&mut config.cookie);
+	Some(
+		match import(&cookie).and_then(|_| user_config::remove_cookie()) {
+			Ok(()) => format!(
+				"{}{}",
+				"Moved the reddit cookie out of config.toml to ",
+				path().display()
+			),
+			Err(err) => {
+				// Until the cookie can be moved, only the user can read it where it is
+				restrict(&user_config::config_path());
+				err
+			}
+		},
+	)
+}
+
+/// Runs `rusddit credentials <name>` for `account` and says what was done.
+pub fn command(name: Option<&str>, account: &str) -> Result<String, String> {
+	let (mut credentials, error) = match name {
+		Some("encrypt" | "decrypt") => {
+			Credentials::from_file(account, || prompt("Passphrase of the reddit cookie: "))
+		}
+		_ => (Credentials::default(), None),
+	};
+	if let Some(err) = error {
+		return Err(err);
+	}
+	match name {
+		Some("encrypt" | "decrypt") if credentials.cookie.is_empty() => {
+			Err("There is no cookie to encrypt, log in with rusddit -c - first".to_string())
+		}
+		Some("encrypt") if credentials.is_encrypted() => {
+			Err("The cookie is already encrypted".to_string())
+		}
+		Some("encrypt") => {
+			let passphrase = prompt("New passphrase: ")?;
+			if passphrase.is_empty() {
+				return Err("The passphrase can't be empty".to_string());
+			}
+			if prompt("Passphrase again: ")? != passphrase {
+				return Err("The passphrases don't match".to_string());
+			}
+			credentials.set_passphrase(Some(passphrase))?;
+			Ok("Encrypted the cookie, rusddit asks for the passphrase on startup".to_string())
+		}
+		Some("decrypt") if !credentials.is_encrypted() => {
+			Err("The cookie isn't encrypted".to_string())
+		}
+		Some("decrypt") => {
+			credentials.set_passphrase(None)?;
+			Ok("Saved the cookie without a passphrase".to_string())
+		}
+		Some("forget") => {
+			forget(account)?;
+			Ok(format!(
+				"{}{}",
+				"Removed the saved cookie of ",
+				user_config::account_name(account)
+			))
+		}
+		Some("path") => Ok(path().display().to_string()),
+		_ => Err("Use rusddit credentials encrypt, decrypt, forget or path".to_string()),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn encrypted_cookie_decrypts_with_its_passphrase() {
+		let cookie = Secret::new("abc123");
+		let passphrase = Secret::new("correct horse");
+		let encrypted = encrypt(&cookie, &passphrase).unwrap();
+		assert!(!encrypted.contains("abc123"));
+		assert_eq!(decrypt(&encrypted, &passphrase).unwrap(), cookie);
+	}
+
+	#[test]
+	fn wrong_passphrase_fails() {
+		let encrypted = encrypt(&Secret::new("abc123"), &Secret::new("correct horse")).unwrap();
+		assert!(decrypt(&encrypted, &Secret::new("battery staple")).is_err());
+	}
+
+	#[test]
+	fn accounts_keep_their_own_entries() {
+		let entry = |cookie: &str| Entry {
+			cookie: cookie.to_string(),
+			..Entry::default()
+		};
+		let mut stored = Stored::default();
+		stored.set("", entry("default"));
+		stored.set("work", entry("work"));
+		assert_eq!(stored.entry("").cookie, "default");
+		assert_eq!(stored.entry("work").cookie, "work");
+		assert_eq!(stored.entry("other").cookie, "");
+		stored.set("work", Entry::default());
+		assert!(!stored.accounts.contains_key("work"));
+		assert_eq!(stored.entry("").cookie, "default");
+		stored.set("", Entry::default());
+		stored.set("home", entry("home"));
+		assert_eq!(stored.entry("").cookie, "");
+		assert_eq!(stored.entry("home").cookie, "home");
+	}
+}
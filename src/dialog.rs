@@ -0,0 +1,178 @@
+use tui::{
+	backend::Backend,
+	layout::{Alignment, Constraint, Direction, Layout, Rect},
+	text::Spans,
+	widgets::{Block, Borders, Clear, List, ListItem, Paragraph, Wrap},
+	Frame,
+};
+
+use crate::app::StatefulList;
+use crate::theme::Theme;
+
+/// Something that changes state on reddit and has to be confirmed first.
+pub enum Action {
+	Hide(String),
+	Unhide(String),
+	Report {
+		thing_id: String,
+		reason: String,
+	},
+	Block(String),
+	Approve(String),
+	Remove {
+		thing_id: String,
+		spam: bool,
+		reason: Option<(String, String)>,
+	},
+	Lock(String, bool),
+	Sticky {
+		thing_id: String,
+		state: bool,
+	},
+	Distinguish(String, bool),
+}
+
+impl Action {
+	pub fn describe(&self) -> String {
+		match self {
+			Action::Hide(_) => "Hide this post?".to_string(),
+			Action::Unhide(_) => "Unhide this post?".to_string(),
+			Action::Report { reason, .. } => format!("{}{}{}", "Report for \"", reason, "\"?"),
+			Action::Block(author) => format!("{}{}{}", "Block u/", author, "?"),
+			Action::Approve(_) => "Approve this item?".to_string(),
+			Action::Remove { spam: true, .. } => "Remove this item as spam?".to_string(),
+			Action::Remove {
+				reason: Some((_, title)),
+				..
+			} => format!("{}{}{}", "Remove this item for \"", title, "\"?"),
+			Action::Remove { .. } => "Remove this item?".to_string(),
+			Action::Lock(_, true) => "Lock this item?".to_string(),
+			Action::Lock(_, false) => "Unlock this item?".to_string(),
+			Action::Sticky { state: true, .. } => "Sticky this item?".to_string(),
+			Action::Sticky { state: false, .. } => "Unsticky this item?".to_string(),
+			Action::Distinguish(_, true) => "Distinguish this item?".to_string(),
+			Action::Distinguish(_, false) => "Undistinguish this item?".to_string(),
+		}
+	}
+}
+
+pub enum Dialog {
+	Confirm {
+		action: Action,
+	},
+	Report {
+		thing_id: String,
+		reasons: StatefulList<String>,
+	},
+	RemovalReason {
+		thing_id: String,
+		reason_ids: Vec<String>,
+		reasons: StatefulList<String>,
+	},
+	Message(String),
+	/// Accounts to switch to, by name.
+	Accounts(StatefulList<String>),
+	/// Asked before quitting when `confirm_quit` is set.
+	Quit,
+}
+
+pub fn draw_dialog<B>(f: &mut Frame<B>, dialog: &mut Dialog, theme: &Theme)
+where
+	B: Backend,
+{
+	match dialog {
+		Dialog::Confirm { action } => {
+			let area = centered_rect(50, 5, f.size());
+			let text = vec![
+				Spans::from(action.describe()),
+				Spans::from(""),
+				Spans::from("[y] yes   [n] no"),
+			];
+			draw_text(f, "Confirm", text, area, theme);
+		}
+		Dialog::Quit => {
+			let area = centered_rect(50, 5, f.size());
+			let text = vec![
+				Spans::from("Quit rusddit?"),
+				Spans::from(""),
+				Spans::from("[y] yes   [n] no"),
+			];
+			draw_text(f, "Confirm", text, area, theme);
+		}
+		Dialog::Message(message) => {
+			let mut text: Vec<Spans> = message.lines().map(Spans::from).collect();
+			text.push(Spans::from(""));
+			text.push(Spans::from("press any key"));
+			let area = centered_rect(50, text.len() as u16 + 2, f.size());
+			draw_text(f, "Rusddit", text, area, theme);
+		}
+		Dialog::Report { reasons, .. } => draw_list(f, "Report reason", reasons, theme),
+		Dialog::RemovalReason { reasons, .. } => draw_list(f, "Removal reason", reasons, theme),
+		Dialog::Accounts(accounts) => draw_list(f, "Account", accounts, theme),
+	}
+}
+
+fn draw_list<B>(f: &mut Frame<B>, title: &str, reasons: &mut StatefulList<String>, theme: &Theme)
+where
+	B: Backend,
+{
+	let height = reasons.items.len() as u16 + 2;
+	let area = centered_rect(60, height, f.size());
+	let items: Vec<ListItem> = reasons
+		.items
+		.iter()
+		.map(|x| ListItem::new(x.as_str()))
+		.collect();
+	let list = List::new(items)
+		.block(Block::default().borders(Borders::ALL).title(title))
+		.style(theme.base())
+		.highlight_style(theme.highlight())
+		.highlight_symbol(">> ");
+	f.render_widget(Clear, area);
+	f.render_stateful_widget(list, area, &mut reasons.state);
+}
+
+fn draw_text<B>(f: &mut Frame<B>, title: &str, text: Vec<Spans>, area: Rect, theme: &Theme)
+where
+	B: Backend,
+{
+	let paragraph = Paragraph::new(text)
+		.style(theme.base())
+		.block(
+			Block::default()
+				.title(title)
+				.borders(Borders::ALL)
+				.border_style(theme.border(true)),
+		)
+		.alignment(Alignment::Center)
+		.wrap(Wrap { trim: true });
+	f.render_widget(Clear, area);
+	f.render_widget(paragraph, area);
+}
+
+/// A rect `percent_x` wide and `height` rows tall in the middle of `r`.
+pub fn centered_rect(percent_x: u16, height: u16, r: Rect) -> Rect {
+	let height = height.min(r.height);
+	let vertical = Layout::default()
+		.direction(Direction::Vertical)
+		.constraints(
+			[
+				Constraint::Length((r.height - height) / 2),
+				Constraint::Length(height),
+				Constraint::Min(0),
+			]
+			.as_ref(),
+		)
+		.split(r);
+	Layout::default()
+		.direction(Direction::Horizontal)
+		.constraints(
+			[
+				Constraint::Percentage((100 - percent_x) / 2),
+				Constraint::Percentage(percent_x),
+				Constraint::Percentage((100 - percent_x) / 2),
+			]
+			.as_ref(),
+		)
+		.split(vertical[1])[1]
+}
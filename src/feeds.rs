@@ -0,0 +1,60 @@
+use crate::history::History;
+use crate::user_config::Sorts;
+
+/// A feed tab, keeping everything needed to come back to it where it was
+/// left. The feed being shown lives in `App` and the feed list, so its entry
+/// here is only brought up to date when another feed is shown.
+#[derive(Default)]
+pub struct Feed {
+	/// Path of the feed as typed into the search box, empty for the front page.
+	pub input: String,
+	pub sort_by: String,
+	pub sort_time: String,
+	pub viewing_hidden: bool,
+	pub posts: Vec<serde_json::Value>,
+	pub selected: Option<usize>,
+	pub list_offset: usize,
+	/// Cursor of the next page.
+	pub last_post_id: String,
+	pub page: usize,
+	/// Feeds are loaded the first time they are shown.
+	pub loaded: bool,
+	/// Where back and forward go in this tab.
+	pub history: History,
+}
+
+impl Feed {
+	/// A feed that isn't loaded yet, sorted by the configured sort.
+	pub fn new(input: &str, sorts: &Sorts) -> Feed {
+		Feed {
+			input: input.to_string(),
+			sort_by: sorts.posts.clone(),
+			sort_time: sorts.time.clone(),
+			page: 1,
+			..Default::default()
+		}
+	}
+}
+
+/// The name of a feed for tabs and the status bar.
+pub fn title(input: &str) -> String {
+	match input {
+		"" => "front page".to_string(),
+		x if x.starts_with("search/") => format!("{}{}", "search: ", &x["search/".len()..]),
+		x => x.to_string(),
+	}
+}
+
+/// Turns what was typed for `:tab` into a feed path: `rust` and `r/rust` are
+/// subreddits, `u/someone` is a user and nothing is the front page.
+pub fn feed_path(typed: &str) -> String {
+	let typed = typed.trim().trim_start_matches('/');
+	match typed {
+		"" | "front" | "frontpage" => "".to_string(),
+		x if x.starts_with("u/") => format!("{}{}{}", "user/", &x[2..], "/submitted"),
+		x if x.starts_with("r/") || x.starts_with("user/") || x.starts_with("search/") => {
+			x.to_string()
+		}
+		x => format!("{}{}", "r/", x),
+	}
+}
@@ -0,0 +1,229 @@
+use regex::{Regex, RegexBuilder};
+use tui::{
+	backend::Backend,
+	layout::Rect,
+	style::{Modifier, Style},
+	text::{Span, Spans},
+	widgets::Paragraph,
+	Frame,
+};
+
+use crate::mouse::Pane;
+use crate::theme::Theme;
+
+/// The `/` search of the post list, the post or the comments. The pane is
+/// searched while it is drawn, so the matches always fit what is on screen.
+pub struct Find {
+	/// The pane searched, the one focused when the search was started.
+	pub pane: Pane,
+	pub query: String,
+	pub case_sensitive: bool,
+	pub regex: bool,
+	/// Whether the query is still being typed.
+	pub typing: bool,
+	/// Which match is the current one.
+	pub current: usize,
+	/// Matches found the last time the pane was drawn.
+	pub count: usize,
+	/// Set when the pane should scroll to the current match on the next draw.
+	jump: bool,
+	/// Whether the query isn't a valid regex.
+	pub invalid: bool,
+}
+
+impl Find {
+	pub fn new(pane: Pane) -> Find {
+		Find {
+			pane,
+			query: String::new(),
+			case_sensitive: false,
+			regex: false,
+			typing: true,
+			current: 0,
+			count: 0,
+			jump: false,
+			invalid: false,
+		}
+	}
+
+	pub fn type_char(&mut self, c: char) {
+		self.query.push(c);
+		self.restart();
+	}
+
+	pub fn delete_char(&mut self) {
+		self.query.pop();
+		self.restart();
+	}
+
+	pub fn toggle_case(&mut self) {
+		self.case_sensitive = !self.case_sensitive;
+		self.restart();
+	}
+
+	pub fn toggle_regex(&mut self) {
+		self.regex = !self.regex;
+		self.restart();
+	}
+
+	/// Goes back to the first match after the query changed.
+	fn restart(&mut self) {
+		self.current = 0;
+		self.jump = true;
+	}
+
+	pub fn next(&mut self) {
+		if self.count > 0 {
+			self.current = (self.current + 1) % self.count;
+			self.jump = true;
+		}
+	}
+
+	pub fn previous(&mut self) {
+		if self.count > 0 {
+			self.current = (self.current + self.count - 1) % self.count;
+			self.jump = true;
+		}
+	}
+
+	fn pattern(&self) -> Result<Regex, regex::Error> {
+		let pattern = match self.regex {
+			true => self.query.clone(),
+			false => regex::escape(&self.query),
+		};
+		RegexBuilder::new(&pattern)
+			.case_insensitive(!self.case_sensitive)
+			.build()
+	}
+
+	/// Highlights the matches in `lines`, the lines of `pane`. Returns the
+	/// line of the current match when the pane should scroll to it.
+	pub fn apply(
+		&mut self,
+		pane: Pane,
+		lines: &mut [Spans<'static>],
+		theme: &Theme,
+	) -> Option<usize> {
+		if pane != self.pane || self.query.is_empty() {
+			return None;
+		}
+		let pattern = match self.pattern() {
+			Ok(x) => x,
+			Err(_) => {
+				self.invalid = true;
+				self.count = 0;
+				return None;
+			}
+		};
+		self.invalid = false;
+		let mut count = 0;
+		let mut current_line = None;
+		for (i, line) in lines.iter_mut().enumerate() {
+			let text: String = line.0.iter().map(|x| x.content.as_ref()).collect();
+			let ranges: Vec<(usize, usize)> = pattern
+				.find_iter(&text)
+				.filter(|x| !x.range().is_empty())
+				.map(|x| (x.start(), x.end()))
+				.collect();
+			if ranges.is_empty() {
+				continue;
+			}
+			let current = match self.current >= count && self.current < count + ranges.len() {
+				true => {
+					current_line = Some(i);
+					Some(self.current - count)
+				}
+				false => None,
+			};
+			*line = highlight(std::mem::take(line), &ranges, current, theme);
+			count += ranges.len();
+		}
+		self.count = count;
+		if self.current >= count {
+			self.current = 0;
+		}
+		match std::mem::take(&mut self.jump) {
+			true => current_line,
+			false => None,
+		}
+	}
+}
+
+/// Splits the spans of `line` so the byte `ranges` of its text are drawn as
+/// matches, `current` being the index of the current match among them.
+fn highlight(
+	line: Spans<'static>,
+	ranges: &[(usize, usize)],
+	current: Option<usize>,
+	theme: &Theme,
+) -> Spans<'static> {
+	let mut spans = vec![];
+	let mut start = 0;
+	for span in line.0 {
+		let text = span.content.into_owned();
+		let end = start + text.len();
+		let mut cut = start;
+		while cut < end {
+			let inside = ranges.iter().position(|x| x.0 <= cut && cut < x.1);
+			let next = match inside {
+				Some(i) => ranges[i].1,
+				None => ranges
+					.iter()
+					.map(|x| x.0)
+					.filter(|x| *x > cut)
+					.min()
+					.unwrap_or(end),
+			}
+			.min(end);
+			let style = match inside {
+				Some(i) => span.style.patch(theme.find_match(current == Some(i))),
+				None => span.style,
+			};
+			spans.push(Span::styled(
+				text[cut - start..next - start].to_string(),
+				style,
+			));
+			cut = next;
+		}
+		start = end;
+	}
+	Spans::from(spans)
+}
+
+/// The `/` prompt at the bottom of the screen, with the switches and the
+/// number of matches on the right.
+pub fn draw_find<B>(f: &mut Frame<B>, find: &Find, area: Rect, theme: &Theme)
+where
+	B: Backend,
+{
+	let dim = Style::default().fg(theme.dim);
+	let on = Style::default()
+		.fg(theme.accent)
+		.add_modifier(Modifier::BOLD);
+	let switch = |state: bool| match state {
+		true => on,
+		false => dim,
+	};
+	let result = match (find.invalid, find.count) {
+		_ if find.query.is_empty() => Span::raw(""),
+		(true, _) => Span::styled("invalid regex ", Style::default().fg(theme.warning)),
+		(false, 0) => Span::styled("no matches ", Style::default().fg(theme.warning)),
+		(false, count) => Span::raw(format!("{}{}{}{}", find.current + 1, "/", count, " ")),
+	};
+	let mut spans = vec![Span::raw("/"), Span::raw(find.query.clone())];
+	let right = vec![
+		result,
+		Span::styled("Aa", switch(find.case_sensitive)),
+		Span::raw(" "),
+		Span::styled(".*", switch(find.regex)),
+		Span::raw(" "),
+	];
+	let right_width = Spans::from(right.clone()).width();
+	let left_width = Spans::from(spans.clone()).width();
+	let gap = (area.width as usize).saturating_sub(left_width + right_width);
+	spans.push(Span::raw(" ".repeat(gap)));
+	spans.extend(right);
+	f.render_widget(Paragraph::new(Spans::from(spans)).style(theme.base()), area);
+	let cursor = area.x + left_width as u16;
+	f.set_cursor(cursor.min(area.right().saturating_sub(1)), area.y);
+}
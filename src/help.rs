@@ -0,0 +1,142 @@
+use tui::{
+	backend::Backend,
+	style::{Modifier, Style},
+	text::{Span, Spans},
+	widgets::{Block, Borders, Clear, List, ListItem, ListState},
+	Frame,
+};
+
+use crate::dialog::centered_rect;
+use crate::keymap::{self, Command, Context, KeyPress, Keymap};
+use crate::theme::Theme;
+
+/// A command and every key bound to it.
+pub struct Entry {
+	pub keys: String,
+	pub command: Command,
+}
+
+/// The `?` overlay, listing the keys of the view it was opened from.
+pub struct Help {
+	pub context: Context,
+	entries: Vec<Entry>,
+	pub search: String,
+	pub state: ListState,
+}
+
+impl Help {
+	pub fn new(keymap: &Keymap, context: Context) -> Help {
+		let mut seen: Vec<&Vec<KeyPress>> = vec![];
+		let mut entries: Vec<Entry> = vec![];
+		for (keys, command) in keymap.bindings(context) {
+			// Keys of the context hide the same keys in Global
+			if seen.contains(&keys) {
+				continue;
+			}
+			seen.push(keys);
+			let described = keymap::describe_keys(keys);
+			match entries.iter_mut().find(|x| x.command == *command) {
+				Some(entry) => entry.keys = format!("{}{}{}", entry.keys, ", ", described),
+				None => entries.push(Entry {
+					keys: described,
+					command: *command,
+				}),
+			}
+		}
+		let mut state = ListState::default();
+		state.select(Some(0));
+		Help {
+			context,
+			entries,
+			search: String::new(),
+			state,
+		}
+	}
+
+	/// The entries whose keys, name or description contain the search.
+	pub fn visible(&self) -> Vec<&Entry> {
+		let search = self.search.to_lowercase();
+		self.entries
+			.iter()
+			.filter(|x| {
+				x.keys.to_lowercase().contains(&search)
+					|| x.command.name().contains(&search)
+					|| x.command.description().to_lowercase().contains(&search)
+			})
+			.collect()
+	}
+
+	pub fn next(&mut self) {
+		let last = self.visible().len().saturating_sub(1);
+		let i = self.state.selected().map_or(0, |x| (x + 1).min(last));
+		self.state.select(Some(i));
+	}
+
+	pub fn previous(&mut self) {
+		let i = self.state.selected().unwrap_or(0).saturating_sub(1);
+		self.state.select(Some(i));
+	}
+
+	pub fn type_char(&mut self, c: char) {
+		self.search.push(c);
+		self.state.select(Some(0));
+	}
+
+	pub fn delete_char(&mut self) {
+		self.search.pop();
+		self.state.select(Some(0));
+	}
+}
+
+pub fn draw_help<B>(f: &mut Frame<B>, help: &mut Help, theme: &Theme)
+where
+	B: Backend,
+{
+	let area = centered_rect(80, f.size().height.saturating_sub(4), f.size());
+	let visible = help.visible();
+	let keys_width = visible
+		.iter()
+		.map(|x| x.keys.chars().count())
+		.max()
+		.unwrap_or(0)
+		.min(24);
+	let mut items: Vec<ListItem> = visible
+		.iter()
+		.map(|entry| {
+			ListItem::new(Spans::from(vec![
+				Span::styled(
+					format!("{:<width$}{}", entry.keys, "  ", width = keys_width),
+					Style::default()
+						.fg(theme.accent)
+						.add_modifier(Modifier::BOLD),
+				),
+				Span::raw(format!("{:<20}", entry.command.name())),
+				Span::styled(entry.command.description(), Style::default().fg(theme.dim)),
+			]))
+		})
+		.collect();
+	if items.is_empty() {
+		items.push(ListItem::new("No keys match the search"));
+	}
+	let title = match help.search.is_empty() {
+		true => format!(
+			"{}{}{}",
+			"Keys in ",
+			help.context.name(),
+			" | type to search  esc close"
+		),
+		false => format!(
+			"{}{}{}{}",
+			"Keys in ",
+			help.context.name(),
+			" | search: ",
+			help.search
+		),
+	};
+	let list = List::new(items)
+		.block(Block::default().borders(Borders::ALL).title(title))
+		.style(theme.base())
+		.highlight_style(theme.highlight());
+	f.render_widget(Clear, area);
+	f.render_stateful_widget(list, area, &mut help.state);
+}
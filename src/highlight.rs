@@ -0,0 +1,121 @@
+use syntect::{
+	easy::HighlightLines,
+	highlighting::{Theme, ThemeSet},
+	parsing::{SyntaxReference, SyntaxSet},
+	util::LinesWithEndings,
+};
+use tui::style::{Color, Style};
+
+pub const DEFAULT_THEME: &str = "base16-ocean.dark";
+
+/// Highlights code blocks with syntect's bundled syntaxes and themes, so it
+/// works without anything installed on the system.
+pub struct Highlighter {
+	syntaxes: SyntaxSet,
+	theme: Theme,
+}
+
+/// Names of the bundled themes.
+pub fn themes() -> Vec<String> {
+	ThemeSet::load_defaults().themes.into_keys().collect()
+}
+
+impl Highlighter {
+	pub fn new(theme_name: &str) -> Highlighter {
+		let mut themes = ThemeSet::load_defaults().themes;
+		let theme = match themes.remove(theme_name) {
+			Some(x) => x,
+			None => themes.remove(DEFAULT_THEME).expect("bundled theme"),
+		};
+		Highlighter {
+			syntaxes: SyntaxSet::load_defaults_newlines(),
+			theme,
+		}
+	}
+
+	pub fn background(&self) -> Option<Color> {
+		self.theme
+			.settings
+			.background
+			.map(|x| Color::Rgb(x.r, x.g, x.b))
+	}
+
+	/// Splits `code` into lines of styled fragments. `language` is the info
+	/// string of a fenced block; without one the language is guessed.
+	pub fn highlight(&self, code: &str, language: Option<&str>) -> Vec<Vec<(Style, String)>> {
+		let syntax = self.syntax(code, language);
+		let mut highlighter = HighlightLines::new(syntax, &self.theme);
+		let background = self.background();
+		LinesWithEndings::from(code)
+			.map(|line| {
+				let ranges = highlighter
+					.highlight_line(line, &self.syntaxes)
+					.unwrap_or_default();
+				ranges
+					.into_iter()
+					.map(|(style, text)| {
+						let fg = style.foreground;
+						let mut tui_style = Style::default().fg(Color::Rgb(fg.r, fg.g, fg.b));
+						if let Some(bg) = background {
+							tui_style = tui_style.bg(bg);
+						}
+						(
+							tui_style,
+							text.trim_end_matches(['\n', '\r']).replace('\t', "    "),
+						)
+					})
+					.collect()
+			})
+			.collect()
+	}
+
+	fn syntax(&self, code: &str, language: Option<&str>) -> &SyntaxReference {
+		let by_token = language
+			.filter(|x| !x.is_empty())
+			.and_then(|x| self.syntaxes.find_syntax_by_token(x));
+		by_token
+			.or_else(|| {
+				self.syntaxes
+					.find_syntax_by_first_line(code.lines().next().unwrap_or_default())
+			})
+			.or_else(|| guess_language(code).and_then(|x| self.syntaxes.find_syntax_by_token(x)))
+			.unwrap_or_else(|| self.syntaxes.find_syntax_plain_text())
+	}
+}
+
+/// A rough guess at the language of an unlabelled code block, based on
+/// keywords that are fairly specific to each language.
+fn guess_language(code: &str) -> Option<&'static str> {
+	let hints: [(&str, &[&str]); 9] = [
+		(
+			"rs",
+			&["fn ", "let mut ", "impl ", "pub fn", "::new(", "println!"],
+		),
+		("py", &["def ", "import ", "elif ", "self.", "print("]),
+		("go", &["func ", "package ", ":= ", "fmt."]),
+		("c", &["#include", "printf(", "int main", "malloc("]),
+		(
+			"cpp",
+			&["std::", "#include <iostream>", "template<", "nullptr"],
+		),
+		(
+			"java",
+			&["public class", "public static void", "System.out"],
+		),
+		(
+			"js",
+			&["function ", "const ", "=> ", "console.log", "require("],
+		),
+		("sh", &["#!/bin/", "echo ", "sudo ", "apt ", "$ "]),
+		("html", &["<div", "<html", "</"]),
+	];
+	hints
+		.iter()
+		.map(|(language, words)| {
+			let score = words.iter().filter(|x| code.contains(*x)).count();
+			(score, *language)
+		})
+		.filter(|x| x.0 > 0)
+		.max_by_key(|x| x.0)
+		.map(|x| x.1)
+}
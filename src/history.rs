@@ -0,0 +1,51 @@
+use crate::feeds::Feed;
+
+/// How many views back can go.
+const HISTORY_SIZE: usize = 50;
+
+/// Everything needed to show a place again as it was left: the feed with its
+/// selection and, if a post was open, its comments and scroll positions.
+pub struct View {
+	pub feed: Feed,
+	pub index: usize,
+	pub comments: serde_json::Value,
+	pub comments_sort_by: String,
+	pub post_scroll: u16,
+	pub comment_scroll: u16,
+	pub code_scroll: u16,
+	pub selected_comment: usize,
+	pub current_focus: usize,
+}
+
+/// The views before and after the one shown, like a browser's history.
+#[derive(Default)]
+pub struct History {
+	back: Vec<View>,
+	forward: Vec<View>,
+}
+
+impl History {
+	/// Remembers `view` before going somewhere new. The views that could be
+	/// gone forward to are dropped.
+	pub fn push(&mut self, view: View) {
+		self.back.push(view);
+		if self.back.len() > HISTORY_SIZE {
+			self.back.remove(0);
+		}
+		self.forward.clear();
+	}
+
+	/// Trades `current` for the view before it, if there is one.
+	pub fn back(&mut self, current: View) -> Option<View> {
+		let view = self.back.pop()?;
+		self.forward.push(current);
+		Some(view)
+	}
+
+	/// Trades `current` for the view that was left by going back.
+	pub fn forward(&mut self, current: View) -> Option<View> {
+		let view = self.forward.pop()?;
+		self.back.push(current);
+		Some(view)
+	}
+}
@@ -0,0 +1,1063 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use std::collections::HashMap;
+
+pub const DEFAULT_PRESET: &str = "default";
+
+/// Where a key is pressed. Keys are looked up in their context first and in
+/// `Global` after that.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Context {
+	Global,
+	Home,
+	Post,
+	Search,
+	Mod,
+	Viewer,
+	Links,
+	Help,
+	CommandLine,
+	Find,
+}
+
+pub const CONTEXTS: [(&str, Context); 10] = [
+	("global", Context::Global),
+	("home", Context::Home),
+	("post", Context::Post),
+	("search", Context::Search),
+	("mod", Context::Mod),
+	("viewer", Context::Viewer),
+	("links", Context::Links),
+	("help", Context::Help),
+	("command_line", Context::CommandLine),
+	("find", Context::Find),
+];
+
+impl Context {
+	pub fn from_name(name: &str) -> Option<Context> {
+		CONTEXTS.iter().find(|x| x.0 == name).map(|x| x.1)
+	}
+
+	pub fn name(&self) -> &'static str {
+		CONTEXTS
+			.iter()
+			.find(|x| x.1 == *self)
+			.map(|x| x.0)
+			.unwrap_or_default()
+	}
+}
+
+/// Everything a key can be bound to. What a command does can depend on the
+/// context, e.g. `sort_top` sorts the feed on Home and the comments on Post.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Command {
+	Quit,
+	NextTab,
+	PreviousTab,
+	Top,
+	Bottom,
+	NextPost,
+	PreviousPost,
+	OpenPost,
+	SortHot,
+	SortNew,
+	SortTop,
+	SortRising,
+	SortBest,
+	SortControversial,
+	ToggleHidden,
+	Hide,
+	Report,
+	Block,
+	ToggleThumbnails,
+	OpenExternal,
+	FocusNext,
+	ScrollDown,
+	ScrollUp,
+	ScrollLeft,
+	ScrollRight,
+	NextComment,
+	PreviousComment,
+	ToggleImages,
+	OpenViewer,
+	OpenLinks,
+	ShrinkList,
+	GrowList,
+	ShrinkPost,
+	GrowPost,
+	Select,
+	DeleteChar,
+	NextItem,
+	PreviousItem,
+	NextListing,
+	Approve,
+	Spam,
+	Remove,
+	Lock,
+	Sticky,
+	Distinguish,
+	Close,
+	NextImage,
+	PreviousImage,
+	ZoomIn,
+	ZoomOut,
+	ResetZoom,
+	PanLeft,
+	PanRight,
+	PanUp,
+	PanDown,
+	Copy,
+	Help,
+	OpenCommandLine,
+	Complete,
+	NextFeed,
+	PreviousFeed,
+	NewFeed,
+	CloseFeed,
+	MoveFeedLeft,
+	MoveFeedRight,
+	Back,
+	Forward,
+	Find,
+	FindNext,
+	FindPrevious,
+	ToggleCase,
+	ToggleRegex,
+	PageDown,
+	PageUp,
+	HalfPageDown,
+	HalfPageUp,
+	CopyPermalink,
+	CopyUrl,
+	CopyTitle,
+	CopyText,
+	CopyComment,
+	CopyCommentLink,
+	SwitchAccount,
+}
+
+const COMMANDS: [(&str, Command, &str); 83] = [
+	("quit", Command::Quit, "Quit rusddit"),
+	("next_tab", Command::NextTab, "Go to the next tab"),
+	(
+		"previous_tab",
+		Command::PreviousTab,
+		"Go to the previous tab",
+	),
+	(
+		"top",
+		Command::Top,
+		"Go to the first post or item, or the top of the pane",
+	),
+	("bottom", Command::Bottom, "Go to the last post or item"),
+	("next_post", Command::NextPost, "Select the next post"),
+	(
+		"previous_post",
+		Command::PreviousPost,
+		"Select the previous post",
+	),
+	("open_post", Command::OpenPost, "Open the selected post"),
+	("sort_hot", Command::SortHot, "Sort by hot"),
+	("sort_new", Command::SortNew, "Sort by new"),
+	("sort_top", Command::SortTop, "Sort by top"),
+	("sort_rising", Command::SortRising, "Sort by rising"),
+	("sort_best", Command::SortBest, "Sort by best"),
+	(
+		"sort_controversial",
+		Command::SortControversial,
+		"Sort by controversial",
+	),
+	(
+		"toggle_hidden",
+		Command::ToggleHidden,
+		"Switch between the feed and your hidden posts",
+	),
+	("hide", Command::Hide, "Hide or unhide the post"),
+	("report", Command::Report, "Report the post or comment"),
+	("block", Command::Block, "Block the author"),
+	(
+		"toggle_thumbnails",
+		Command::ToggleThumbnails,
+		"Show or hide thumbnails",
+	),
+	(
+		"open_external",
+		Command::OpenExternal,
+		"Open the link with an opener",
+	),
+	(
+		"focus_next",
+		Command::FocusNext,
+		"Move focus to the other pane",
+	),
+	("scroll_down", Command::ScrollDown, "Scroll down"),
+	("scroll_up", Command::ScrollUp, "Scroll up"),
+	(
+		"scroll_left",
+		Command::ScrollLeft,
+		"Scroll code blocks left",
+	),
+	(
+		"scroll_right",
+		Command::ScrollRight,
+		"Scroll code blocks right",
+	),
+	(
+		"next_comment",
+		Command::NextComment,
+		"Select the next comment",
+	),
+	(
+		"previous_comment",
+		Command::PreviousComment,
+		"Select the previous comment",
+	),
+	(
+		"toggle_images",
+		Command::ToggleImages,
+		"Show or hide images",
+	),
+	("open_viewer", Command::OpenViewer, "View the post's images"),
+	(
+		"open_links",
+		Command::OpenLinks,
+		"Pick a link from the post or comment",
+	),
+	(
+		"shrink_list",
+		Command::ShrinkList,
+		"Make the post list narrower",
+	),
+	("grow_list", Command::GrowList, "Make the post list wider"),
+	("shrink_post", Command::ShrinkPost, "Make the post smaller"),
+	("grow_post", Command::GrowPost, "Make the post bigger"),
+	("select", Command::Select, "Select the highlighted entry"),
+	(
+		"delete_char",
+		Command::DeleteChar,
+		"Delete the last character",
+	),
+	("next_item", Command::NextItem, "Select the next entry"),
+	(
+		"previous_item",
+		Command::PreviousItem,
+		"Select the previous entry",
+	),
+	(
+		"next_listing",
+		Command::NextListing,
+		"Switch between the mod queue, reports and other listings",
+	),
+	("approve", Command::Approve, "Approve the item"),
+	("spam", Command::Spam, "Remove the item as spam"),
+	("remove", Command::Remove, "Remove the item with a reason"),
+	("lock", Command::Lock, "Lock or unlock the item"),
+	("sticky", Command::Sticky, "Sticky or unsticky the item"),
+	(
+		"distinguish",
+		Command::Distinguish,
+		"Distinguish the item or take that back",
+	),
+	("close", Command::Close, "Close the overlay"),
+	("next_image", Command::NextImage, "Show the next image"),
+	(
+		"previous_image",
+		Command::PreviousImage,
+		"Show the previous image",
+	),
+	("zoom_in", Command::ZoomIn, "Zoom in"),
+	("zoom_out", Command::ZoomOut, "Zoom out"),
+	(
+		"reset_zoom",
+		Command::ResetZoom,
+		"Fit the image to the screen",
+	),
+	("pan_left", Command::PanLeft, "Pan left"),
+	("pan_right", Command::PanRight, "Pan right"),
+	("pan_up", Command::PanUp, "Pan up"),
+	("pan_down", Command::PanDown, "Pan down"),
+	("copy", Command::Copy, "Copy the link"),
+	("help", Command::Help, "List the keys of the current view"),
+	(
+		"command_line",
+		Command::OpenCommandLine,
+		"Open the command line",
+	),
+	(
+		"complete",
+		Command::Complete,
+		"Complete the word being typed",
+	),
+	("next_feed", Command::NextFeed, "Show the next feed tab"),
+	(
+		"previous_feed",
+		Command::PreviousFeed,
+		"Show the previous feed tab",
+	),
+	("new_feed", Command::NewFeed, "Open a feed in a new tab"),
+	("close_feed", Command::CloseFeed, "Close the feed tab"),
+	(
+		"move_feed_left",
+		Command::MoveFeedLeft,
+		"Move the feed tab to the left",
+	),
+	(
+		"move_feed_right",
+		Command::MoveFeedRight,
+		"Move the feed tab to the right",
+	),
+	("back", Command::Back, "Go back to the previous view"),
+	(
+		"forward",
+		Command::Forward,
+		"Go forward to the view left by going back",
+	),
+	(
+		"find",
+		Command::Find,
+		"Search the text of the list, post or comments",
+	),
+	(
+		"find_next",
+		Command::FindNext,
+		"Go to the next match of the search",
+	),
+	(
+		"find_previous",
+		Command::FindPrevious,
+		"Go to the previous match of the search",
+	),
+	(
+		"toggle_case",
+		Command::ToggleCase,
+		"Toggle whether the search is case sensitive",
+	),
+	(
+		"toggle_regex",
+		Command::ToggleRegex,
+		"Toggle whether the search is a regex",
+	),
+	(
+		"page_down",
+		Command::PageDown,
+		"Scroll down a page, or move down a page of posts",
+	),
+	(
+		"page_up",
+		Command::PageUp,
+		"Scroll up a page, or move up a page of posts",
+	),
+	(
+		"half_page_down",
+		Command::HalfPageDown,
+		"Scroll down half a page",
+	),
+	("half_page_up", Command::HalfPageUp, "Scroll up half a page"),
+	(
+		"copy_permalink",
+		Command::CopyPermalink,
+		"Copy the link to the post's comments",
+	),
+	(
+		"copy_url",
+		Command::CopyUrl,
+		"Copy the link the post points to",
+	),
+	(
+		"copy_title",
+		Command::CopyTitle,
+		"Copy the title of the post",
+	),
+	("copy_text", Command::CopyText, "Copy the text of the post"),
+	(
+		"copy_comment",
+		Command::CopyComment,
+		"Copy the text of the selected comment",
+	),
+	(
+		"copy_comment_link",
+		Command::CopyCommentLink,
+		"Copy the link to the selected comment",
+	),
+	(
+		"switch_account",
+		Command::SwitchAccount,
+		"Switch to another account",
+	),
+];
+
+/// Names of all commands, for completion.
+pub fn command_names() -> Vec<&'static str> {
+	COMMANDS.iter().map(|x| x.0).collect()
+}
+
+impl Command {
+	pub fn from_name(name: &str) -> Option<Command> {
+		COMMANDS.iter().find(|x| x.0 == name).map(|x| x.1)
+	}
+
+	pub fn name(&self) -> &'static str {
+		COMMANDS
+			.iter()
+			.find(|x| x.1 == *self)
+			.map(|x| x.0)
+			.unwrap_or_default()
+	}
+
+	/// What the command does, for the help overlay.
+	pub fn description(&self) -> &'static str {
+		COMMANDS
+			.iter()
+			.find(|x| x.1 == *self)
+			.map(|x| x.2)
+			.unwrap_or_default()
+	}
+
+	/// Whether the command may wait on a request to reddit.
+	pub fn loads(&self) -> bool {
+		matches!(
+			self,
+			Command::NextTab
+				| Command::PreviousTab
+				| Command::Top
+				| Command::Bottom
+				| Command::NextPost
+				| Command::PreviousPost
+				| Command::OpenPost
+				| Command::SortHot
+				| Command::SortNew
+				| Command::SortTop
+				| Command::SortRising
+				| Command::SortBest
+				| Command::SortControversial
+				| Command::ToggleHidden
+				| Command::Report
+				| Command::Select
+				| Command::NextListing
+				| Command::PageDown
+				| Command::HalfPageDown
+				| Command::NextFeed
+				| Command::PreviousFeed
+				| Command::CloseFeed
+				| Command::Approve
+				| Command::Remove
+				| Command::Lock
+				| Command::Sticky
+				| Command::Distinguish
+		)
+	}
+
+	/// Whether a count in front of the key repeats the command. Only movement
+	/// does, `5o` shouldn't open five browser windows.
+	pub fn takes_count(&self) -> bool {
+		matches!(
+			self,
+			Command::NextTab
+				| Command::PreviousTab
+				| Command::NextPost
+				| Command::PreviousPost
+				| Command::ScrollDown
+				| Command::ScrollUp
+				| Command::ScrollLeft
+				| Command::ScrollRight
+				| Command::NextComment
+				| Command::PreviousComment
+				| Command::NextItem
+				| Command::PreviousItem
+				| Command::NextImage
+				| Command::PreviousImage
+				| Command::ZoomIn
+				| Command::ZoomOut
+				| Command::PanLeft
+				| Command::PanRight
+				| Command::PanUp
+				| Command::PanDown
+				| Command::NextFeed
+				| Command::PreviousFeed
+				| Command::MoveFeedLeft
+				| Command::MoveFeedRight
+				| Command::Back
+				| Command::Forward
+				| Command::FindNext
+				| Command::FindPrevious
+				| Command::PageDown
+				| Command::PageUp
+				| Command::HalfPageDown
+				| Command::HalfPageUp
+				| Command::ShrinkList
+				| Command::GrowList
+				| Command::ShrinkPost
+				| Command::GrowPost
+		)
+	}
+}
+
+/// A single key press. Shift is part of the character, so `G` is shift+g.
+#[derive(Clone, Copy, PartialEq)]
+pub struct KeyPress {
+	pub code: KeyCode,
+	pub ctrl: bool,
+	pub alt: bool,
+}
+
+impl From<KeyEvent> for KeyPress {
+	fn from(event: KeyEvent) -> KeyPress {
+		KeyPress {
+			code: event.code,
+			ctrl: event.modifiers.contains(KeyModifiers::CONTROL),
+			alt: event.modifiers.contains(KeyModifiers::ALT),
+		}
+	}
+}
+
+impl KeyPress {
+	fn plain(code: KeyCode) -> KeyPress {
+		KeyPress {
+			code,
+			ctrl: false,
+			alt: false,
+		}
+	}
+
+	/// The character typed, for keys without ctrl or alt.
+	pub fn char(&self) -> Option<char> {
+		match self.code {
+			KeyCode::Char(c) if !self.ctrl && !self.alt => Some(c),
+			_ => None,
+		}
+	}
+}
+
+const NAMED_KEYS: [(&str, KeyCode); 18] = [
+	("esc", KeyCode::Esc),
+	("enter", KeyCode::Enter),
+	("cr", KeyCode::Enter),
+	("tab", KeyCode::Tab),
+	("s-tab", KeyCode::BackTab),
+	("space", KeyCode::Char(' ')),
+	("bs", KeyCode::Backspace),
+	("backspace", KeyCode::Backspace),
+	("del", KeyCode::Delete),
+	("up", KeyCode::Up),
+	("down", KeyCode::Down),
+	("left", KeyCode::Left),
+	("right", KeyCode::Right),
+	("pageup", KeyCode::PageUp),
+	("pagedown", KeyCode::PageDown),
+	("home", KeyCode::Home),
+	("end", KeyCode::End),
+	("lt", KeyCode::Char('<')),
+];
+
+fn parse_named(name: &str) -> Option<KeyPress> {
+	let mut key = KeyPress::plain(KeyCode::Null);
+	let mut rest = name;
+	loop {
+		let lower = rest.to_lowercase();
+		if lower.starts_with("c-") && rest.len() > 2 {
+			key.ctrl = true;
+		} else if (lower.starts_with("a-") || lower.starts_with("m-")) && rest.len() > 2 {
+			key.alt = true;
+		} else {
+			break;
+		}
+		rest = &rest[2..];
+	}
+	let lower = rest.to_lowercase();
+	key.code = match NAMED_KEYS.iter().find(|x| x.0 == lower) {
+		Some(x) => x.1,
+		None if lower == "gt" => KeyCode::Char('>'),
+		None if lower.starts_with('f') && lower.len() > 1 => KeyCode::F(lower[1..].parse().ok()?),
+		None if rest.chars().count() == 1 => KeyCode::Char(rest.chars().next()?),
+		None => return None,
+	};
+	Some(key)
+}
+
+/// Parses keys written the way vim does: `gg`, `G`, `<C-d>`, `<Esc>`, `<A-lt>`.
+pub fn parse_keys(spec: &str) -> Result<Vec<KeyPress>, String> {
+	let invalid = || format!("{}{}", "Invalid key: ", spec);
+	if spec.is_empty() {
+		return Err(invalid());
+	}
+	let mut keys = vec![];
+	let mut rest = spec;
+	while let Some(c) = rest.chars().next() {
+		let named = match c {
+			'<' if rest.len() > 1 => rest.find('>').map(|end| (&rest[1..end], end)),
+			_ => None,
+		};
+		match named {
+			Some((name, end)) => {
+				keys.push(parse_named(name).ok_or_else(invalid)?);
+				rest = &rest[end + 1..];
+			}
+			None => {
+				keys.push(KeyPress::plain(KeyCode::Char(c)));
+				rest = &rest[c.len_utf8()..];
+			}
+		}
+	}
+	Ok(keys)
+}
+
+/// Writes keys back in the notation `parse_keys` reads.
+pub fn describe_keys(keys: &[KeyPress]) -> String {
+	let mut text = String::new();
+	for key in keys {
+		let name = match key.code {
+			KeyCode::Char('<') => "lt".to_string(),
+			KeyCode::Char(' ') => "Space".to_string(),
+			KeyCode::Char(c) => c.to_string(),
+			KeyCode::F(n) => format!("{}{}", "F", n),
+			code => {
+				let name = NAMED_KEYS
+					.iter()
+					.find(|x| x.1 == code)
+					.map(|x| x.0)
+					.unwrap_or("?");
+				let mut chars = name.chars();
+				match chars.next() {
+					Some(first) => format!("{}{}", first.to_uppercase(), chars.as_str()),
+					None => String::new(),
+				}
+			}
+		};
+		let plain_char = matches!(key.code, KeyCode::Char(c) if c != '<' && c != ' ');
+		if plain_char && !key.ctrl && !key.alt {
+			text.push_str(&name);
+			continue;
+		}
+		text.push('<');
+		if key.ctrl {
+			text.push_str("C-");
+		}
+		if key.alt {
+			text.push_str("A-");
+		}
+		text.push_str(&name);
+		text.push('>');
+	}
+	text
+}
+
+type Bindings = Vec<(Vec<KeyPress>, Command)>;
+
+/// The result of feeding a key to the keymap.
+pub enum Resolved {
+	Command(Command, usize),
+	/// The key started a sequence or a count, more keys are needed.
+	Pending,
+	/// Nothing is bound to the key, e.g. a letter typed into the search box.
+	Unbound(KeyPress),
+}
+
+/// Keys typed so far that don't make a whole binding yet.
+#[derive(Default)]
+pub struct KeyState {
+	pending: Vec<KeyPress>,
+	count: Option<usize>,
+}
+
+pub struct Keymap {
+	bindings: HashMap<Context, Bindings>,
+}
+
+fn default_bindings() -> Vec<(Context, &'static str, &'static str)> {
+	use Context::*;
+	vec![
+		(Global, "q", "quit"),
+		(Global, "<C-c>", "quit"),
+		(Global, "<Esc>", "back"),
+		(Global, "<Backspace>", "back"),
+		(Global, "<A-Left>", "back"),
+		(Global, "<A-Right>", "forward"),
+		(Global, "1", "previous_tab"),
+		(Global, "2", "next_tab"),
+		(Global, "<Left>", "previous_tab"),
+		(Global, "<Right>", "next_tab"),
+		(Global, "?", "help"),
+		(Global, "<F1>", "help"),
+		(Global, ":", "command_line"),
+		(Global, "/", "find"),
+		(Global, "A", "switch_account"),
+		(Home, "<Right>", "open_post"),
+		(Home, "<Up>", "previous_post"),
+		(Home, "<Down>", "next_post"),
+		(Home, "h", "sort_hot"),
+		(Home, "b", "sort_best"),
+		(Home, "c", "sort_controversial"),
+		(Home, "w", "sort_new"),
+		(Home, "T", "sort_top"),
+		(Home, "R", "sort_rising"),
+		(Home, "H", "toggle_hidden"),
+		(Home, "x", "hide"),
+		(Home, "r", "report"),
+		(Home, "B", "block"),
+		(Home, "t", "toggle_thumbnails"),
+		(Home, "o", "open_external"),
+		(Home, "{", "shrink_list"),
+		(Home, "}", "grow_list"),
+		(Home, "(", "shrink_post"),
+		(Home, ")", "grow_post"),
+		(Home, "<Tab>", "next_feed"),
+		(Home, "<S-Tab>", "previous_feed"),
+		(Home, "<C-t>", "new_feed"),
+		(Home, "C", "close_feed"),
+		(Home, "<lt>", "move_feed_left"),
+		(Home, ">", "move_feed_right"),
+		(Home, "n", "find_next"),
+		(Home, "N", "find_previous"),
+		(Home, "<PageDown>", "page_down"),
+		(Home, "<PageUp>", "page_up"),
+		(Home, "<C-d>", "half_page_down"),
+		(Home, "<C-u>", "half_page_up"),
+		(Home, "<Home>", "top"),
+		(Home, "<End>", "bottom"),
+		(Home, "yy", "copy_permalink"),
+		(Home, "yu", "copy_url"),
+		(Home, "yt", "copy_title"),
+		(Home, "ys", "copy_text"),
+		(Post, "<Tab>", "focus_next"),
+		(Post, "j", "scroll_down"),
+		(Post, "k", "scroll_up"),
+		(Post, "<lt>", "scroll_left"),
+		(Post, ">", "scroll_right"),
+		(Post, "]", "next_comment"),
+		(Post, "[", "previous_comment"),
+		(Post, "<Up>", "previous_post"),
+		(Post, "<Down>", "next_post"),
+		(Post, "h", "sort_hot"),
+		(Post, "b", "sort_best"),
+		(Post, "c", "sort_controversial"),
+		(Post, "T", "sort_top"),
+		(Post, "i", "toggle_images"),
+		(Post, "f", "open_viewer"),
+		(Post, "l", "open_links"),
+		(Post, "o", "open_external"),
+		(Post, "x", "hide"),
+		(Post, "r", "report"),
+		(Post, "B", "block"),
+		(Post, "{", "shrink_list"),
+		(Post, "}", "grow_list"),
+		(Post, "(", "shrink_post"),
+		(Post, ")", "grow_post"),
+		(Post, "n", "find_next"),
+		(Post, "N", "find_previous"),
+		(Post, "<PageDown>", "page_down"),
+		(Post, "<PageUp>", "page_up"),
+		(Post, "<C-d>", "half_page_down"),
+		(Post, "<C-u>", "half_page_up"),
+		(Post, "<Home>", "top"),
+		(Post, "<End>", "bottom"),
+		(Post, "yy", "copy_permalink"),
+		(Post, "yu", "copy_url"),
+		(Post, "yt", "copy_title"),
+		(Post, "ys", "copy_text"),
+		(Post, "yc", "copy_comment"),
+		(Post, "yl", "copy_comment_link"),
+		(Search, "<Enter>", "select"),
+		(Search, "<Backspace>", "delete_char"),
+		(Mod, "<Tab>", "focus_next"),
+		(Mod, "<Up>", "previous_item"),
+		(Mod, "<Down>", "next_item"),
+		(Mod, "<Enter>", "select"),
+		(Mod, "l", "next_listing"),
+		(Mod, "a", "approve"),
+		(Mod, "s", "spam"),
+		(Mod, "d", "remove"),
+		(Mod, "L", "lock"),
+		(Mod, "S", "sticky"),
+		(Mod, "D", "distinguish"),
+		(Viewer, "<Esc>", "close"),
+		(Viewer, "q", "close"),
+		(Viewer, "f", "close"),
+		(Viewer, "<Right>", "next_image"),
+		(Viewer, "<Space>", "next_image"),
+		(Viewer, "<Left>", "previous_image"),
+		(Viewer, "<Backspace>", "previous_image"),
+		(Viewer, "]", "next_post"),
+		(Viewer, "[", "previous_post"),
+		(Viewer, "+", "zoom_in"),
+		(Viewer, "=", "zoom_in"),
+		(Viewer, "-", "zoom_out"),
+		(Viewer, "0", "reset_zoom"),
+		(Viewer, "h", "pan_left"),
+		(Viewer, "l", "pan_right"),
+		(Viewer, "k", "pan_up"),
+		(Viewer, "j", "pan_down"),
+		(Viewer, "i", "toggle_images"),
+		(Viewer, "?", "help"),
+		(Links, "<Esc>", "close"),
+		(Links, "<Up>", "previous_item"),
+		(Links, "<Down>", "next_item"),
+		(Links, "<Enter>", "select"),
+		(Links, "o", "open_external"),
+		(Links, "y", "copy"),
+		(Links, "?", "help"),
+		(Help, "<Esc>", "close"),
+		(Help, "<F1>", "close"),
+		(Help, "<Up>", "previous_item"),
+		(Help, "<Down>", "next_item"),
+		(Help, "<Backspace>", "delete_char"),
+		(CommandLine, "<Esc>", "close"),
+		(CommandLine, "<Enter>", "select"),
+		(CommandLine, "<Backspace>", "delete_char"),
+		(CommandLine, "<Tab>", "complete"),
+		(CommandLine, "<Up>", "previous_item"),
+		(CommandLine, "<Down>", "next_item"),
+		(Find, "<Esc>", "close"),
+		(Find, "<Enter>", "select"),
+		(Find, "<Backspace>", "delete_char"),
+		(Find, "<A-c>", "toggle_case"),
+		(Find, "<A-r>", "toggle_regex"),
+	]
+}
+
+/// Changes the vim preset makes to the default bindings. `none` unbinds.
+fn vim_bindings() -> Vec<(Context, &'static str, &'static str)> {
+	use Context::*;
+	vec![
+		// Digits are counts in vim
+		(Global, "1", "none"),
+		(Global, "2", "none"),
+		(Global, "<C-o>", "back"),
+		(Global, "gt", "next_tab"),
+		(Global, "gT", "previous_tab"),
+		(Home, "j", "next_post"),
+		(Home, "k", "previous_post"),
+		(Home, "l", "open_post"),
+		(Home, "gg", "top"),
+		(Home, "G", "bottom"),
+		(Home, "h", "none"),
+		(Home, "b", "none"),
+		(Home, "c", "none"),
+		(Home, "w", "none"),
+		(Home, "T", "none"),
+		(Home, "R", "none"),
+		(Home, "sh", "sort_hot"),
+		(Home, "sb", "sort_best"),
+		(Home, "sc", "sort_controversial"),
+		(Home, "sn", "sort_new"),
+		(Home, "st", "sort_top"),
+		(Home, "sr", "sort_rising"),
+		(Home, "<C-f>", "page_down"),
+		(Home, "<C-b>", "page_up"),
+		(Post, "gg", "top"),
+		(Post, "G", "bottom"),
+		(Post, "<C-f>", "page_down"),
+		(Post, "<C-b>", "page_up"),
+		(Post, "J", "next_post"),
+		(Post, "K", "previous_post"),
+		(Post, "h", "none"),
+		(Post, "b", "none"),
+		(Post, "c", "none"),
+		(Post, "T", "none"),
+		(Post, "sh", "sort_hot"),
+		(Post, "sb", "sort_best"),
+		(Post, "sc", "sort_controversial"),
+		(Post, "st", "sort_top"),
+		(Post, "zh", "scroll_left"),
+		(Post, "zl", "scroll_right"),
+		(Mod, "j", "next_item"),
+		(Mod, "k", "previous_item"),
+		(Mod, "gg", "top"),
+		(Mod, "G", "bottom"),
+		(Links, "<C-n>", "next_item"),
+		(Links, "<C-p>", "previous_item"),
+	]
+}
+
+fn emacs_bindings() -> Vec<(Context, &'static str, &'static str)> {
+	use Context::*;
+	vec![
+		(Global, "<C-x><C-c>", "quit"),
+		(Global, "<A-f>", "next_tab"),
+		(Global, "<A-b>", "previous_tab"),
+		(Global, "<C-x><Left>", "back"),
+		(Global, "<C-x><Right>", "forward"),
+		(Home, "<C-n>", "next_post"),
+		(Home, "<C-p>", "previous_post"),
+		(Home, "<A-lt>", "top"),
+		(Home, "<A-gt>", "bottom"),
+		(Home, "<C-f>", "open_post"),
+		(Home, "<C-v>", "page_down"),
+		(Home, "<A-v>", "page_up"),
+		(Home, "<A-w>", "copy_permalink"),
+		(Post, "<C-n>", "scroll_down"),
+		(Post, "<C-p>", "scroll_up"),
+		(Post, "<A-n>", "next_comment"),
+		(Post, "<A-p>", "previous_comment"),
+		(Post, "<A-lt>", "top"),
+		(Post, "<C-f>", "scroll_right"),
+		(Post, "<C-b>", "scroll_left"),
+		(Post, "<C-v>", "page_down"),
+		(Post, "<A-v>", "page_up"),
+		(Post, "<A-w>", "copy_permalink"),
+		(Post, "<A-gt>", "bottom"),
+		(Mod, "<C-n>", "next_item"),
+		(Mod, "<C-p>", "previous_item"),
+		(Mod, "<A-lt>", "top"),
+		(Mod, "<A-gt>", "bottom"),
+		(Viewer, "<C-g>", "close"),
+		(Viewer, "<C-f>", "next_image"),
+		(Viewer, "<C-b>", "previous_image"),
+		(Links, "<C-g>", "close"),
+		(Links, "<C-n>", "next_item"),
+		(Links, "<C-p>", "previous_item"),
+	]
+}
+
+pub const PRESETS: [&str; 3] = ["default", "vim", "emacs"];
+
+impl Keymap {
+	/// Builds the keymap of a preset with the user's bindings on top. `keys`
+	/// maps context names to key/command pairs, e.g. `"post": {"J": "next_post"}`.
+	/// Mistakes are returned as errors and skipped.
+	pub fn load(
+		preset: &str,
+		keys: &HashMap<String, HashMap<String, String>>,
+	) -> (Keymap, Vec<String>) {
+		let mut keymap = Keymap {
+			bindings: HashMap::new(),
+		};
+		let mut errors = vec![];
+		let mut rules = default_bindings();
+		match preset {
+			"default" => {}
+			"vim" => rules.extend(vim_bindings()),
+			"emacs" => rules.extend(emacs_bindings()),
+			_ => errors.push(format!(
+				"{}{}{}{}",
+				"Unknown keymap preset: ",
+				preset,
+				", choose one of ",
+				PRESETS.join(", ")
+			)),
+		}
+		for (context, spec, command) in rules {
+			if let Err(err) = keymap.bind(context, spec, command) {
+				errors.push(err);
+			}
+		}
+		for (context_name, bindings) in keys {
+			let context = match Context::from_name(context_name) {
+				Some(x) => x,
+				None => {
+					errors.push(format!("{}{}", "Unknown key context: ", context_name));
+					continue;
+				}
+			};
+			for (spec, command) in bindings {
+				if let Err(err) = keymap.bind(context, spec, command) {
+					errors.push(err);
+				}
+			}
+		}
+		(keymap, errors)
+	}
+
+	/// Binds `spec` to the command called `command` in `context`, replacing
+	/// what was bound to it. The command `none` removes the binding.
+	pub fn bind(&mut self, context: Context, spec: &str, command: &str) -> Result<(), String> {
+		let keys = parse_keys(spec)?;
+		let command = match command {
+			"none" => None,
+			name => Some(
+				Command::from_name(name)
+					.ok_or_else(|| format!("{}{}", "Unknown command: ", name))?,
+			),
+		};
+		let bindings = self.bindings.entry(context).or_default();
+		bindings.retain(|x| x.0 != keys);
+		if let Some(command) = command {
+			bindings.push((keys, command));
+		}
+		Ok(())
+	}
+
+	/// The bindings that apply in `context`, its own ones first. Overlays
+	/// don't use the global bindings and the search box leaves plain
+	/// characters to the text.
+	pub fn bindings(&self, context: Context) -> Vec<&(Vec<KeyPress>, Command)> {
+		let own = self.bindings.get(&context).into_iter().flatten();
+		let global = self
+			.bindings
+			.get(&Context::Global)
+			.into_iter()
+			.flatten()
+			.filter(move |x| context != Context::Search || x.0[0].char().is_none());
+		match context {
+			Context::Global
+			| Context::Viewer
+			| Context::Links
+			| Context::Help
+			| Context::CommandLine
+			| Context::Find => own.collect(),
+			_ => own.chain(global).collect(),
+		}
+	}
+
+	/// The first keys bound to `command` in `context`, written for display.
+	pub fn keys_for(&self, context: Context, command: Command) -> Option<String> {
+		self.bindings(context)
+			.into_iter()
+			.find(|x| x.1 == command)
+			.map(|x| describe_keys(&x.0))
+	}
+
+	fn lookup(&self, context: Context, keys: &[KeyPress]) -> Option<Command> {
+		self.bindings(context)
+			.into_iter()
+			.find(|x| x.0 == keys)
+			.map(|x| x.1)
+	}
+
+	/// Feeds one key press. Usually one result comes back, but a key that
+	/// breaks off a sequence can also complete the binding before it.
+	pub fn feed(&self, state: &mut KeyState, context: Context, key: KeyPress) -> Vec<Resolved> {
+		let typing = matches!(
+			context,
+			Context::Search | Context::Links | Context::Help | Context::CommandLine | Context::Find
+		);
+		if state.pending.is_empty() && !typing {
+			if let Some(digit) = key.char().and_then(|x| x.to_digit(10)) {
+				let counting = digit > 0 || state.count.is_some();
+				if counting && self.lookup(context, &[key]).is_none() {
+					let count = state.count.unwrap_or(0) * 10 + digit as usize;
+					state.count = Some(count.min(9999));
+					return vec![Resolved::Pending];
+				}
+			}
+		}
+		state.pending.push(key);
+		let candidates: Vec<_> = self
+			.bindings(context)
+			.into_iter()
+			.filter(|x| x.0.starts_with(&state.pending))
+			.collect();
+		if candidates.iter().any(|x| x.0.len() > state.pending.len()) {
+			return vec![Resolved::Pending];
+		}
+		if let Some(binding) = candidates.first() {
+			state.pending.clear();
+			return vec![Resolved::Command(
+				binding.1,
+				state.count.take().unwrap_or(1),
+			)];
+		}
+		let pending = std::mem::take(&mut state.pending);
+		if pending.len() > 1 {
+			let mut results = vec![];
+			match self.lookup(context, &pending[..pending.len() - 1]) {
+				Some(command) => {
+					results.push(Resolved::Command(command, state.count.take().unwrap_or(1)))
+				}
+				None => state.count = None,
+			}
+			results.extend(self.feed(state, context, key));
+			return results;
+		}
+		state.count = None;
+		vec![Resolved::Unbound(key)]
+	}
+}
@@ -0,0 +1,122 @@
+use serde::{Deserialize, Serialize};
+use tui::{
+	backend::Backend,
+	layout::{Alignment, Constraint, Direction, Layout, Rect},
+	widgets::{Paragraph, Wrap},
+	Frame,
+};
+
+use crate::theme::Theme;
+
+/// Below this size nothing but a warning is drawn.
+pub const MIN_WIDTH: u16 = 40;
+pub const MIN_HEIGHT: u16 = 12;
+/// From this width on the list, post and comments are shown side by side.
+pub const WIDE_WIDTH: u16 = 150;
+
+const MIN_PERCENT: u16 = 15;
+const MAX_PERCENT: u16 = 85;
+
+/// How the screen is split between panes, in percent. Changed with the
+/// resize keys and saved in the config.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct PaneSizes {
+	/// Width of the post list when panes are side by side.
+	#[serde(default = "default_list_width")]
+	pub list_width: u16,
+	/// Share of the post in the post/comments split.
+	#[serde(default = "default_post_size")]
+	pub post_size: u16,
+}
+
+fn default_list_width() -> u16 {
+	30
+}
+
+fn default_post_size() -> u16 {
+	45
+}
+
+impl Default for PaneSizes {
+	fn default() -> PaneSizes {
+		PaneSizes {
+			list_width: default_list_width(),
+			post_size: default_post_size(),
+		}
+	}
+}
+
+impl PaneSizes {
+	pub fn resize_list(&mut self, delta: i16) {
+		self.list_width = resize(self.list_width, delta);
+	}
+
+	pub fn resize_post(&mut self, delta: i16) {
+		self.post_size = resize(self.post_size, delta);
+	}
+}
+
+fn resize(percent: u16, delta: i16) -> u16 {
+	(percent as i16 + delta).clamp(MIN_PERCENT as i16, MAX_PERCENT as i16) as u16
+}
+
+/// Splits off the last row of the screen for the status bar.
+pub fn split_status_bar(area: Rect) -> (Rect, Rect) {
+	let parts = Layout::default()
+		.direction(Direction::Vertical)
+		.constraints([Constraint::Min(0), Constraint::Length(1)].as_ref())
+		.split(area);
+	(parts[0], parts[1])
+}
+
+pub fn too_small(area: Rect) -> bool {
+	area.width < MIN_WIDTH || area.height < MIN_HEIGHT
+}
+
+pub fn is_wide(area: Rect) -> bool {
+	area.width >= WIDE_WIDTH
+}
+
+/// Splits `area` in two, giving the first part `percent` of it.
+pub fn split(area: Rect, direction: Direction, percent: u16) -> (Rect, Rect) {
+	let parts = Layout::default()
+		.direction(direction)
+		.constraints([Constraint::Percentage(percent), Constraint::Min(0)].as_ref())
+		.split(area);
+	(parts[0], parts[1])
+}
+
+/// A `width` by `height` rect in the middle of `area`, shrunk to fit.
+pub fn centered(width: u16, height: u16, area: Rect) -> Rect {
+	let width = width.min(area.width);
+	let height = height.min(area.height);
+	Rect {
+		x: area.x + (area.width - width) / 2,
+		y: area.y + (area.height - height) / 2,
+		width,
+		height,
+	}
+}
+
+pub fn draw_too_small<B>(f: &mut Frame<B>, theme: &Theme)
+where
+	B: Backend,
+{
+	let size = f.size();
+	let message = format!(
+		"{}{}{}{}{}{}{}{}",
+		"The terminal is too small (",
+		size.width,
+		"x",
+		size.height,
+		"), rusddit needs at least ",
+		MIN_WIDTH,
+		"x",
+		MIN_HEIGHT
+	);
+	let paragraph = Paragraph::new(message)
+		.style(theme.base())
+		.alignment(Alignment::Center)
+		.wrap(Wrap { trim: true });
+	f.render_widget(paragraph, centered(size.width, 3, size));
+}
@@ -0,0 +1,261 @@
+use pulldown_cmark::{Event, Parser, Tag};
+use tui::{
+	backend::Backend,
+	style::{Modifier, Style},
+	text::{Span, Spans},
+	widgets::{Block, Borders, Clear, List, ListItem},
+	Frame,
+};
+
+use crate::app::StatefulList;
+use crate::dialog::centered_rect;
+use crate::markdown::decode_entities;
+use crate::theme::Theme;
+
+/// Hint keys, leaving out the keys the picker uses for its actions.
+const HINT_KEYS: &str = "asdfghjklqwertuipzxcvbnm";
+
+#[derive(Clone, PartialEq)]
+pub struct Link {
+	pub label: String,
+	pub url: String,
+}
+
+/// A place inside reddit that rusddit can show itself.
+pub enum Target {
+	Subreddit(String),
+	User(String),
+	Thread {
+		post_id: String,
+		comment_id: Option<String>,
+	},
+}
+
+fn reddit_url(path: &str) -> String {
+	format!(
+		"{}{}",
+		"https://www.reddit.com/",
+		path.trim_start_matches('/')
+	)
+}
+
+/// Finds `r/name` and `u/name` references in plain text, with or without a
+/// leading slash.
+fn find_references(text: &str, links: &mut Vec<Link>) {
+	let chars: Vec<char> = text.chars().collect();
+	let mut i = 0;
+	while i + 2 < chars.len() {
+		let starts_word = i == 0 || !(chars[i - 1].is_alphanumeric() || chars[i - 1] == '_');
+		if starts_word && (chars[i] == 'r' || chars[i] == 'u') && chars[i + 1] == '/' {
+			let name: String = chars[i + 2..]
+				.iter()
+				.take_while(|x| x.is_alphanumeric() || **x == '_' || **x == '-')
+				.collect();
+			if !name.is_empty() {
+				let kind = match chars[i] {
+					'r' => "r/",
+					_ => "u/",
+				};
+				let label = format!("{}{}", kind, name);
+				links.push(Link {
+					url: reddit_url(&label),
+					label,
+				});
+				i += name.chars().count() + 2;
+				continue;
+			}
+		}
+		i += 1;
+	}
+}
+
+/// Finds bare `http(s)://` URLs in plain text. Returns the text without them
+/// so their paths aren't mistaken for r/ and u/ references.
+fn find_urls(text: &str, links: &mut Vec<Link>) -> String {
+	let mut remaining = String::new();
+	let mut rest = text;
+	while let Some(start) = rest.find("http") {
+		let candidate = &rest[start..];
+		remaining.push_str(&rest[..start]);
+		if !(candidate.starts_with("http://") || candidate.starts_with("https://")) {
+			remaining.push_str("http");
+			rest = &rest[start + 4..];
+			continue;
+		}
+		let end = candidate
+			.find(|x: char| x.is_whitespace() || x == '<' || x == '>' || x == '"')
+			.unwrap_or(candidate.len());
+		let url = candidate[..end].trim_end_matches(['.', ',', ')', ';', ':', '!', '?']);
+		links.push(Link {
+			label: url.to_string(),
+			url: url.to_string(),
+		});
+		rest = &candidate[end..];
+	}
+	remaining.push_str(rest);
+	remaining
+}
+
+/// Every link in a markdown text: markdown links, bare URLs and r/ and u/
+/// references, in the order they appear.
+pub fn extract_links(markdown: &str) -> Vec<Link> {
+	let source = decode_entities(markdown);
+	let mut links = vec![];
+	let mut current: Option<(String, String)> = None;
+	for event in Parser::new(&source) {
+		match event {
+			Event::Start(Tag::Link(_, url, _)) => current = Some((url.to_string(), String::new())),
+			Event::End(Tag::Link(..)) => {
+				if let Some((url, label)) = current.take() {
+					let url = match url.starts_with('/') {
+						true => reddit_url(&url),
+						false => url,
+					};
+					links.push(Link {
+						label: match label.is_empty() {
+							true => url.clone(),
+							false => label,
+						},
+						url,
+					});
+				}
+			}
+			Event::Text(text) | Event::Code(text) => match &mut current {
+				Some((_, label)) => label.push_str(&text),
+				None => {
+					let text = find_urls(&text, &mut links);
+					find_references(&text, &mut links);
+				}
+			},
+			_ => {}
+		}
+	}
+	links
+}
+
+/// Works out whether `url` points somewhere rusddit can show, like a
+/// subreddit, a user or a comment thread.
+pub fn reddit_target(url: &str) -> Option<Target> {
+	let rest = url.split("://").nth(1).unwrap_or(url);
+	let (host, path) = rest.split_once('/').unwrap_or((rest, ""));
+	let host = host.to_lowercase();
+	let path = path.split(['?', '#']).next().unwrap_or_default();
+	let parts: Vec<&str> = path.split('/').filter(|x| !x.is_empty()).collect();
+	if host == "redd.it" {
+		return parts.first().map(|x| Target::Thread {
+			post_id: x.to_string(),
+			comment_id: None,
+		});
+	}
+	if host != "reddit.com" && !host.ends_with(".reddit.com") {
+		return None;
+	}
+	match parts.as_slice() {
+		["r", _, "comments", post_id, _, comment_id, ..] => Some(Target::Thread {
+			post_id: post_id.to_string(),
+			comment_id: Some(comment_id.to_string()),
+		}),
+		["r", _, "comments", post_id, ..] | ["comments", post_id, ..] => Some(Target::Thread {
+			post_id: post_id.to_string(),
+			comment_id: None,
+		}),
+		["r", subreddit, ..] => Some(Target::Subreddit(subreddit.to_string())),
+		["u", user, ..] | ["user", user, ..] => Some(Target::User(user.to_string())),
+		_ => None,
+	}
+}
+
+/// Hint labels for `count` links: single keys while they last, pairs after that.
+fn hints(count: usize) -> Vec<String> {
+	let keys: Vec<char> = HINT_KEYS.chars().collect();
+	if count <= keys.len() {
+		return keys.iter().take(count).map(|x| x.to_string()).collect();
+	}
+	keys.iter()
+		.flat_map(|a| keys.iter().map(move |b| format!("{}{}", a, b)))
+		.take(count)
+		.collect()
+}
+
+pub struct LinkPicker {
+	pub links: StatefulList<Link>,
+	pub hints: Vec<String>,
+	pub typed: String,
+}
+
+impl LinkPicker {
+	pub fn new(mut links: Vec<Link>) -> LinkPicker {
+		let mut seen = vec![];
+		links.retain(|x| {
+			let new = !seen.contains(&x.url);
+			seen.push(x.url.clone());
+			new
+		});
+		LinkPicker {
+			hints: hints(links.len()),
+			links: StatefulList::with_items(links),
+			typed: String::new(),
+		}
+	}
+
+	pub fn selected(&self) -> Option<&Link> {
+		self.links.items.get(self.links.state.selected()?)
+	}
+
+	/// Adds a typed hint key. Returns true once the keys typed so far spell out
+	/// a whole hint, which then becomes the selected link.
+	pub fn type_hint(&mut self, c: char) -> bool {
+		self.typed.push(c);
+		if let Some(i) = self.hints.iter().position(|x| *x == self.typed) {
+			self.links.state.select(Some(i));
+			self.typed.clear();
+			return true;
+		}
+		if !self.hints.iter().any(|x| x.starts_with(&self.typed)) {
+			self.typed.clear();
+		}
+		false
+	}
+}
+
+pub fn draw_link_picker<B>(f: &mut Frame<B>, picker: &mut LinkPicker, theme: &Theme)
+where
+	B: Backend,
+{
+	let height = picker.links.items.len() as u16 + 3;
+	let area = centered_rect(80, height, f.size());
+	let items: Vec<ListItem> = picker
+		.links
+		.items
+		.iter()
+		.zip(picker.hints.iter())
+		.map(|(link, hint)| {
+			let mut spans = vec![
+				Span::styled(
+					format!("{}{}{}", "[", hint, "] "),
+					Style::default()
+						.fg(theme.accent)
+						.add_modifier(Modifier::BOLD),
+				),
+				Span::raw(link.label.clone()),
+			];
+			if link.label != link.url {
+				spans.push(Span::styled(
+					format!("{}{}", "  ", link.url),
+					Style::default().fg(theme.dim),
+				));
+			}
+			ListItem::new(Spans::from(spans))
+		})
+		.collect();
+	let list = List::new(items)
+		.block(
+			Block::default()
+				.borders(Borders::ALL)
+				.title("Links | enter follow  o open  y copy  esc close"),
+		)
+		.style(theme.base())
+		.highlight_style(theme.highlight());
+	f.render_widget(Clear, area);
+	f.render_stateful_widget(list, area, &mut picker.links.state);
+}
@@ -1,9 +1,33 @@
+mod accounts;
 mod app;
+mod clipboard;
+mod command_line;
+mod credentials;
+mod dialog;
+mod feeds;
+mod find;
+mod help;
+mod highlight;
+mod history;
+mod keymap;
+mod layout;
+mod links;
+mod markdown;
+mod media;
+mod moderation;
+mod mouse;
+mod opener;
+mod post_info;
+mod scroll;
+mod status;
 mod tabs;
+mod theme;
 mod user_config;
 mod utils;
+mod viewer;
 
 use crate::app::App;
+use crate::credentials::Credentials;
 use crate::user_config::UserConfig;
 
 use crossterm::{
@@ -11,86 +35,117 @@ use crossterm::{
 	execute,
 	terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
-use std::{error::Error, fs, io, path::Path};
+use std::{error::Error, io, path::PathBuf};
 use tui::{
 	backend::{Backend, CrosstermBackend},
-	style::{Color, Modifier, Style},
+	layout::Rect,
+	style::Style,
 	text::{Span, Spans},
 	widgets::{Block, Borders, Tabs},
 	Frame, Terminal,
 };
 
-static mut LAST_POST_ID: String = String::new();
-
 fn main() -> Result<(), Box<dyn Error>> {
-	let args: Vec<String> = std::env::args().collect();
+	let mut args: Vec<String> = std::env::args().collect();
 	let mut subreddit: String = "".to_owned();
-	let path = Path::join(
-		home::home_dir().expect("what").as_path(),
-		Path::new(".config/rusddit/"),
-	);
-	let mut user_config: UserConfig = UserConfig::new();
-	if Path::exists(Path::join(path.as_path(), "config.txt").as_path()) == false {
-		match fs::create_dir_all(path.as_path()) {
-			Ok(_x) => {
-				let _file = std::fs::File::create(Path::join(path.as_path(), "config.txt"))
-					.expect("Shouldnt happen");
+	let mut no_mouse = false;
+	if let Some(i) = args.iter().position(|x| x == "--config") {
+		match args.get(i + 1) {
+			Some(path) => user_config::set_config_path(PathBuf::from(path)),
+			None => {
+				println!("--config needs the path of a config file");
+				return Ok(());
 			}
-			Err(_x) => {
-				println!("{}", "Couldn't create cookie file")
+		}
+		args.drain(i..i + 2);
+	}
+	let mut account = None;
+	if let Some(i) = args.iter().position(|x| x == "--account") {
+		match args.get(i + 1) {
+			Some(name) => account = Some(user_config::account_key(name).to_string()),
+			None => {
+				println!("--account needs the name of an account");
+				return Ok(());
 			}
 		}
+		args.drain(i..i + 2);
 	}
+	if args.get(1).is_some_and(|x| x == "config") {
+		match args.get(2).map(|x| x.as_str()) {
+			Some("init") => match user_config::init() {
+				Ok(path) => println!("Wrote the default config to {}", path.display()),
+				Err(err) => println!("{}", err),
+			},
+			Some("path") => println!("{}", user_config::config_path().display()),
+			_ => println!("Use rusddit config init to write the default config and rusddit config path to see where it goes"),
+		}
+		return Ok(());
+	}
+	if args.get(1).is_some_and(|x| x == "credentials") {
+		let account = account
+			.unwrap_or_else(|| user_config::account_key(&UserConfig::load().account).to_string());
+		match credentials::command(args.get(2).map(|x| x.as_str()), &account) {
+			Ok(x) | Err(x) => println!("{}", x),
+		}
+		return Ok(());
+	}
+	let mut new_cookie = None;
 	for i in 0..args.len() {
 		if args[i] == "-c" || args[i] == "--cookie" {
-			//TODO implement tabs
-			user_config.changeConfig(Some(args[i + 1].to_string()), None);
+			match args.get(i + 1).map(|x| x.as_str()) {
+				Some("-") | None => new_cookie = Some(credentials::read_stdin()),
+				Some(_) => {
+					println!("A cookie on the command line ends up in your shell history and ps, pass it on standard input with -c - or run with RUSDDIT_COOKIE set");
+					return Ok(());
+				}
+			}
 		}
 		if args[i] == "-s" || args[i] == "--subreddit" {
 			subreddit = format!("{}{}", "r/", args[i + 1].clone());
 		}
+		if args[i] == "--no-mouse" {
+			no_mouse = true;
+		}
 		if args[i] == "-h" || args[i] == "--help" {
-			print!("rusddit is a terminal client for reddit written in rust\n\t-s <subreddit>\t--subreddit <subreddit>\tstart in given subreddit\n\t-c <cookie>\t--cookie <cookie>\t save reddit cookie for sessions");
+			print!("rusddit is a terminal client for reddit written in rust\n\t-s <subreddit>\t--subreddit <subreddit>\tstart in given subreddit\n\t-c -\t\t--cookie -\t\tsave the reddit cookie read from standard input\n\t\t--no-mouse\t\tleave the mouse to the terminal for selecting text\n\t\t--config <file>\t\tread the config from file instead of config.toml\n\t\t--account <name>\tuse the named account, adding it if it is new\n\tconfig init\t\t\twrite the default config with every setting explained\n\tconfig path\t\t\tshow where the config is read from\n\tcredentials encrypt\t\tencrypt the saved cookie with a passphrase\n\tcredentials decrypt\t\tsave the cookie without a passphrase again\n\tcredentials forget\t\tremove the saved cookie\nThe credentials commands and -c work on the account of --account.\nThe cookie is saved in credentials.toml next to the config, only readable by you\nRUSDDIT_COOKIE runs with another cookie without saving it and RUSDDIT_PASSPHRASE\nunlocks an encrypted cookie without asking\nThe config is $XDG_CONFIG_HOME/rusddit/config.toml, or ~/.config/rusddit/config.toml\nPress ? in rusddit to see the keys of the current view and : to type commands\nLines of rusddit.rc next to config.toml are run as commands on startup\n");
 			return Ok(());
 		}
 	}
-	// let reddit_cookie = match std::fs::read_to_string(Path::join(
-	// 	home::home_dir().expect("what").as_path(),
-	// 	Path::new(".config/rusddit/cookie.txt"),
-	// )) {
-	// 	Ok(x) => x,
-	// 	Err(_x) => {
-	// 		println!("Couldn't find cookie file. Starting an anonymous session");
-	// 		"".to_string()
-	// 	}
-	// };
-	user_config.readConfig();
-	let reddit_cookie = user_config.cookie;
-	let m: Vec<serde_json::Value>;
-	unsafe {
-		m = utils::get_posts(
-			subreddit.to_owned(),
-			false,
-			"hot".to_string(),
-			&mut LAST_POST_ID,
-			reddit_cookie.clone(),
-		);
+	let migrated = user_config::migrate();
+	let mut user_config = UserConfig::load();
+	user_config.messages.extend(migrated);
+	if let Some(note) = credentials::migrate(&mut user_config) {
+		user_config.messages.push(note);
+	}
+	let account =
+		account.unwrap_or_else(|| user_config::account_key(&user_config.account).to_string());
+	let (mut credentials, error) = Credentials::load(&account);
+	user_config.messages.extend(error);
+	if let Some(Err(err)) = new_cookie.map(|x| x.and_then(|x| credentials.save(x))) {
+		user_config.messages.push(err);
 	}
+	utils::configure(&user_config.network);
+	if no_mouse {
+		user_config.mouse = false;
+	}
+	let mut last_post_id = String::new();
+	let mut app = App::new(&user_config, credentials);
+	let m: Vec<serde_json::Value> = app.open_feeds(&subreddit, &mut last_post_id);
 
 	// setup terminal
 	enable_raw_mode()?;
 	let mut stdout = io::stdout();
-	execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
+	execute!(stdout, EnterAlternateScreen)?;
+	if user_config.mouse {
+		execute!(stdout, EnableMouseCapture)?;
+	}
 	let backend = CrosstermBackend::new(stdout);
 	let mut terminal = Terminal::new(backend)?;
 
-	// create app and run it
-	let app = App::new(reddit_cookie.clone());
+	// run the app
+	app.load_identity();
 	// app.update_comments(m.clone(), 0);
-	let res;
-	unsafe {
-		res = app::run_app(&mut terminal, app, m, &mut LAST_POST_ID);
-	}
+	let res = app::run_app(&mut terminal, app, m, &mut last_post_id);
 
 	// restore terminal
 	disable_raw_mode()?;
@@ -108,47 +163,81 @@ fn main() -> Result<(), Box<dyn Error>> {
 	Ok(())
 }
 
-fn ui<B: Backend>(f: &mut Frame<B>, mut app: &mut App, v: &Vec<serde_json::Value>) {
+fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App, v: &[serde_json::Value]) {
 	let mut posts: Vec<(String, usize)> = Vec::new();
-	for i in 0..v.len() {
+	for (i, post) in v.iter().enumerate() {
 		let formatted = format!(
-			"{}{}",
-			v[i as usize]["data"]["subreddit_name_prefixed"], v[i as usize]["data"]["title"]
+			"{}{}{}",
+			post["data"]["subreddit_name_prefixed"]
+				.as_str()
+				.unwrap_or_default(),
+			" ",
+			markdown::decode_entities(post["data"]["title"].as_str().unwrap_or_default())
 		);
-		posts.push((formatted, i as usize));
+		posts.push((formatted, i));
 	}
 
 	app.items.items = posts.clone();
 	let size = f.size();
+	app.regions = mouse::Regions {
+		tabs: Rect {
+			height: 3.min(size.height),
+			..size
+		},
+		..Default::default()
+	};
 
-	let block = Block::default().style(Style::default().bg(Color::Black).fg(Color::White));
+	let theme = &app.theme;
+	let block = Block::default().style(theme.base());
 	f.render_widget(block, size);
+	if layout::too_small(size) {
+		layout::draw_too_small(f, theme);
+		app.images.hide();
+		return;
+	}
 	let titles = app
 		.titles
 		.iter()
 		.map(|t| {
 			let (first, rest) = t.split_at(1);
 			Spans::from(vec![
-				Span::styled(first, Style::default().fg(Color::Yellow)),
-				Span::styled(rest, Style::default().fg(Color::Green)),
+				Span::styled(first, Style::default().fg(theme.accent)),
+				Span::styled(rest, Style::default().fg(theme.tab)),
 			])
 		})
 		.collect();
 	let tabs = Tabs::new(titles)
 		.block(Block::default().borders(Borders::ALL).title("Tabs"))
 		.select(app.index)
-		.style(Style::default().fg(Color::Cyan))
-		.highlight_style(
-			Style::default()
-				.add_modifier(Modifier::BOLD)
-				.bg(Color::White)
-				.fg(Color::Black),
-		);
+		.style(Style::default().fg(theme.tab))
+		.highlight_style(theme.selected_tab());
 
+	let (main, status_bar) = layout::split_status_bar(size);
 	match app.index {
-		0 => tabs::draw_first_tab(f, &mut app, v, tabs),
-		1 => tabs::draw_second_tab(f, &mut app, v, tabs),
-		2 => tabs::draw_third_tab(f, &mut app, tabs),
+		0 => tabs::draw_first_tab(f, app, v, tabs, main),
+		1 => tabs::draw_second_tab(f, app, v, tabs, main),
+		2 => tabs::draw_third_tab(f, app, tabs, main),
+		3 => tabs::draw_fourth_tab(f, app, tabs, main),
 		_ => unreachable!(),
 	};
+	match (app.command_line_open, &app.find) {
+		(true, _) => command_line::draw_command_line(f, &app.command_line, status_bar, &app.theme),
+		(_, Some(x)) if x.typing => find::draw_find(f, x, status_bar, &app.theme),
+		_ => status::draw_status_bar(f, app, v, status_bar),
+	}
+	if let Some(viewer) = &app.viewer {
+		viewer::draw_viewer(f, viewer, &mut app.images, v, &app.theme);
+	}
+	if let Some(picker) = &mut app.link_picker {
+		links::draw_link_picker(f, picker, &app.theme);
+		app.images.hide();
+	}
+	if let Some(help) = &mut app.help {
+		help::draw_help(f, help, &app.theme);
+		app.images.hide();
+	}
+	if let Some(dialog) = &mut app.dialog {
+		dialog::draw_dialog(f, dialog, &app.theme);
+		app.images.hide();
+	}
 }
@@ -0,0 +1,518 @@
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag};
+use tui::{
+	style::{Modifier, Style},
+	text::{Span, Spans, Text},
+};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::highlight::Highlighter;
+use crate::theme::Theme;
+
+/// reddit escapes `&`, `<` and `>` in the markdown it hands out, and titles and
+/// bodies can hold other entities like `&quot;`, `&#39;` or `&#x200B;`, so the
+/// text has to be unescaped once before it is parsed (or shown as is, e.g.
+/// titles). Anything that isn't a known entity is left as it is.
+pub fn decode_entities(text: &str) -> String {
+	let mut decoded = String::with_capacity(text.len());
+	let mut rest = text;
+	while let Some(start) = rest.find('&') {
+		decoded.push_str(&rest[..start]);
+		rest = &rest[start..];
+		let entity = rest[1..]
+			.find(';')
+			.filter(|x| *x <= 10)
+			.and_then(|end| Some((end, decode_entity(&rest[1..end + 1])?)));
+		match entity {
+			Some((end, x)) => {
+				decoded.push(x);
+				rest = &rest[end + 2..];
+			}
+			None => {
+				decoded.push('&');
+				rest = &rest[1..];
+			}
+		}
+	}
+	decoded.push_str(rest);
+	decoded
+}
+
+/// The character of an entity's name, `amp` or `#39` or `#x200B`.
+fn decode_entity(name: &str) -> Option<char> {
+	let code = match name.strip_prefix('#') {
+		Some(x) => match x.strip_prefix(['x', 'X']) {
+			Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+			None => x.parse().ok()?,
+		},
+		None => {
+			return match name {
+				"amp" => Some('&'),
+				"lt" => Some('<'),
+				"gt" => Some('>'),
+				"quot" => Some('"'),
+				"apos" => Some('\''),
+				"nbsp" => Some('\u{a0}'),
+				_ => None,
+			}
+		}
+	};
+	char::from_u32(code)
+}
+
+/// Renders reddit flavoured markdown into styled lines no wider than `width`
+/// where the layout depends on it (tables and horizontal rules). Code blocks
+/// are highlighted and never wrapped; lines wider than the pane are cut off
+/// and can be scrolled sideways with `code_scroll`, up to the columns the
+/// widest of them is wider than the pane, which are returned with the text.
+pub fn render(
+	markdown: &str,
+	width: u16,
+	highlighter: &Highlighter,
+	theme: &Theme,
+	code_scroll: u16,
+) -> (Text<'static>, u16) {
+	let mut options = Options::empty();
+	options.insert(Options::ENABLE_TABLES);
+	options.insert(Options::ENABLE_STRIKETHROUGH);
+	let source = decode_entities(markdown);
+	let mut renderer = Renderer::new(width as usize, highlighter, theme, code_scroll as usize);
+	for event in Parser::new_ext(&source, options) {
+		renderer.event(event);
+	}
+	renderer.flush();
+	while renderer.lines.last().map(|x| x.width() == 0) == Some(true) {
+		renderer.lines.pop();
+	}
+	let overflow = renderer.code_overflow.min(u16::MAX as usize) as u16;
+	(Text::from(renderer.lines), overflow)
+}
+
+struct ListLevel {
+	next_number: Option<u64>,
+	marker_width: usize,
+}
+
+#[derive(Default)]
+struct Table {
+	rows: Vec<Vec<String>>,
+	cell: String,
+	header_rows: usize,
+}
+
+struct Renderer<'a> {
+	highlighter: &'a Highlighter,
+	theme: &'a Theme,
+	code_scroll: usize,
+	/// Columns the widest line of code doesn't fit by.
+	code_overflow: usize,
+	lines: Vec<Spans<'static>>,
+	current: Vec<Span<'static>>,
+	styles: Vec<Style>,
+	quote_depth: usize,
+	lists: Vec<ListLevel>,
+	item_marker: Option<String>,
+	/// Language and text of the code block being read, if any.
+	code_block: Option<(Option<String>, String)>,
+	table: Option<Table>,
+	width: usize,
+}
+
+impl<'a> Renderer<'a> {
+	fn new(
+		width: usize,
+		highlighter: &'a Highlighter,
+		theme: &'a Theme,
+		code_scroll: usize,
+	) -> Renderer<'a> {
+		Renderer {
+			highlighter,
+			theme,
+			code_scroll,
+			code_overflow: 0,
+			lines: vec![],
+			current: vec![],
+			styles: vec![Style::default()],
+			quote_depth: 0,
+			lists: vec![],
+			item_marker: None,
+			code_block: None,
+			table: None,
+			width,
+		}
+	}
+
+	fn style(&self) -> Style {
+		*self.styles.last().unwrap_or(&Style::default())
+	}
+
+	fn push_style(&mut self, style: Style) {
+		self.styles.push(self.style().patch(style));
+	}
+
+	fn pop_style(&mut self) {
+		if self.styles.len() > 1 {
+			self.styles.pop();
+		}
+	}
+
+	/// Quote bars and list indentation that go in front of every line.
+	fn prefix(&mut self) -> Vec<Span<'static>> {
+		let mut spans = vec![];
+		if self.quote_depth > 0 {
+			spans.push(Span::styled(
+				"│ ".repeat(self.quote_depth),
+				Style::default().fg(self.theme.quote),
+			));
+		}
+		if let Some(level) = self.lists.last() {
+			let indent: usize = self.lists[..self.lists.len() - 1]
+				.iter()
+				.map(|x| x.marker_width)
+				.sum();
+			spans.push(Span::raw(match self.item_marker.take() {
+				Some(marker) => format!("{}{}", " ".repeat(indent), marker),
+				None => " ".repeat(indent + level.marker_width),
+			}));
+		}
+		spans
+	}
+
+	fn prefix_width(&self) -> usize {
+		let lists: usize = self.lists.iter().map(|x| x.marker_width).sum();
+		self.quote_depth * 2 + lists
+	}
+
+	fn flush(&mut self) {
+		if self.current.is_empty() && self.item_marker.is_none() {
+			return;
+		}
+		let mut line = self.prefix();
+		line.append(&mut self.current);
+		self.lines.push(Spans::from(line));
+	}
+
+	/// Separates blocks with an empty line, except inside list items.
+	fn block_gap(&mut self) {
+		self.flush();
+		let last_blank = self.lines.last().map(|x| x.width() == 0);
+		if last_blank == Some(false) && self.lists.is_empty() {
+			self.lines.push(Spans::default());
+		}
+	}
+
+	fn push_line(&mut self, spans: Vec<Span<'static>>) {
+		self.current = spans;
+		if self.current.is_empty() {
+			self.current.push(Span::raw(""));
+		}
+		self.flush();
+	}
+
+	fn text(&mut self, text: &str) {
+		if let Some(table) = &mut self.table {
+			table.cell.push_str(text);
+			return;
+		}
+		if let Some((_, code)) = &mut self.code_block {
+			code.push_str(text);
+			return;
+		}
+		let style = self.style();
+		self.current.push(Span::styled(text.to_string(), style));
+	}
+
+	fn code(&mut self, code: &str, language: Option<&str>) {
+		let width = self.width.saturating_sub(self.prefix_width());
+		let background = self.theme.adapt_style(Style {
+			bg: self.highlighter.background(),
+			..Style::default()
+		});
+		for line in self.highlighter.highlight(code, language) {
+			let line = line
+				.into_iter()
+				.map(|(style, text)| (self.theme.adapt_style(style), text))
+				.collect::<Vec<_>>();
+			let line_width: usize = line.iter().map(|x| x.1.width()).sum();
+			self.code_overflow = self.code_overflow.max(line_width.saturating_sub(width));
+			let mut spans = scroll_line(line, self.code_scroll, width, self.theme);
+			let used: usize = spans.iter().map(|x| x.width()).sum();
+			if used < width {
+				spans.push(Span::styled(" ".repeat(width - used), background));
+			}
+			self.push_line(spans);
+		}
+	}
+
+	fn event(&mut self, event: Event) {
+		match event {
+			Event::Start(tag) => self.start(tag),
+			Event::End(tag) => self.end(tag),
+			Event::Text(text) | Event::Html(text) => self.text(&text),
+			Event::Code(code) => {
+				if let Some(table) = &mut self.table {
+					table.cell.push_str(&code);
+				} else {
+					self.current
+						.push(Span::styled(code.to_string(), self.theme.inline_code()));
+				}
+			}
+			Event::FootnoteReference(name) => self.text(&format!("{}{}{}", "[", name, "]")),
+			Event::SoftBreak => self.text(" "),
+			Event::HardBreak => self.flush(),
+			Event::Rule => {
+				self.block_gap();
+				let width = self.width.saturating_sub(self.prefix_width());
+				self.push_line(vec![Span::styled(
+					"─".repeat(width),
+					Style::default().fg(self.theme.dim),
+				)]);
+			}
+			Event::TaskListMarker(done) => self.text(match done {
+				true => "[x] ",
+				false => "[ ] ",
+			}),
+		}
+	}
+
+	fn start(&mut self, tag: Tag) {
+		match tag {
+			Tag::Paragraph => {
+				if self.item_marker.is_none() {
+					self.block_gap();
+				}
+			}
+			Tag::Heading(level, ..) => {
+				self.block_gap();
+				let mut style = Style::default()
+					.fg(self.theme.accent)
+					.add_modifier(Modifier::BOLD);
+				if level == HeadingLevel::H1 {
+					style = style.add_modifier(Modifier::UNDERLINED);
+				}
+				self.push_style(style);
+			}
+			Tag::BlockQuote => {
+				self.block_gap();
+				self.quote_depth += 1;
+				self.push_style(Style::default().fg(self.theme.quote));
+			}
+			Tag::CodeBlock(kind) => {
+				self.block_gap();
+				let language = match kind {
+					CodeBlockKind::Fenced(info) => {
+						info.split_whitespace().next().map(|x| x.to_string())
+					}
+					CodeBlockKind::Indented => None,
+				};
+				self.code_block = Some((language, String::new()));
+			}
+			Tag::List(first) => {
+				if self.lists.is_empty() {
+					self.block_gap();
+				} else {
+					self.flush();
+				}
+				self.lists.push(ListLevel {
+					next_number: first,
+					marker_width: 2,
+				});
+			}
+			Tag::Item => {
+				self.flush();
+				if let Some(level) = self.lists.last_mut() {
+					let marker = match level.next_number {
+						Some(n) => {
+							level.next_number = Some(n + 1);
+							format!("{}{}", n, ". ")
+						}
+						None => "• ".to_string(),
+					};
+					level.marker_width = level.marker_width.max(marker.width());
+					self.item_marker = Some(marker);
+				}
+			}
+			Tag::Emphasis => self.push_style(Style::default().add_modifier(Modifier::ITALIC)),
+			Tag::Strong => self.push_style(Style::default().add_modifier(Modifier::BOLD)),
+			Tag::Strikethrough => {
+				self.push_style(Style::default().add_modifier(Modifier::CROSSED_OUT))
+			}
+			Tag::Link(..) | Tag::Image(..) => self.push_style(
+				Style::default()
+					.fg(self.theme.link)
+					.add_modifier(Modifier::UNDERLINED),
+			),
+			Tag::Table(_) => {
+				self.block_gap();
+				self.table = Some(Table::default());
+			}
+			Tag::TableHead | Tag::TableRow => {
+				if let Some(table) = &mut self.table {
+					table.rows.push(vec![]);
+				}
+			}
+			Tag::TableCell => {}
+			Tag::FootnoteDefinition(name) => {
+				self.block_gap();
+				self.text(&format!("{}{}{}", "[", name, "]: "));
+			}
+		}
+	}
+
+	fn end(&mut self, tag: Tag) {
+		match tag {
+			Tag::Paragraph => self.flush(),
+			Tag::Heading(..) => {
+				self.flush();
+				self.pop_style();
+			}
+			Tag::BlockQuote => {
+				self.flush();
+				self.quote_depth -= 1;
+				self.pop_style();
+			}
+			Tag::CodeBlock(_) => {
+				if let Some((language, code)) = self.code_block.take() {
+					self.code(&code, language.as_deref());
+				}
+			}
+			Tag::List(_) => {
+				self.flush();
+				self.lists.pop();
+			}
+			Tag::Item => self.flush(),
+			Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link(..) | Tag::Image(..) => {
+				self.pop_style()
+			}
+			Tag::TableHead => {
+				if let Some(table) = &mut self.table {
+					table.header_rows = table.rows.len();
+				}
+			}
+			Tag::TableCell => {
+				if let Some(table) = &mut self.table {
+					let cell = std::mem::take(&mut table.cell);
+					if let Some(row) = table.rows.last_mut() {
+						row.push(cell);
+					}
+				}
+			}
+			Tag::Table(_) => {
+				if let Some(table) = self.table.take() {
+					let width = self.width.saturating_sub(self.prefix_width());
+					for line in layout_table(table, width, self.theme) {
+						self.push_line(line);
+					}
+				}
+			}
+			_ => {}
+		}
+	}
+}
+
+/// Cuts the columns `offset..offset + width` out of a highlighted line, marking
+/// cut off text on either side with an arrow.
+fn scroll_line(
+	line: Vec<(Style, String)>,
+	offset: usize,
+	width: usize,
+	theme: &Theme,
+) -> Vec<Span<'static>> {
+	let total: usize = line.iter().map(|x| x.1.width()).sum();
+	let mut spans = vec![];
+	let mut column = 0;
+	for (style, text) in line {
+		let mut visible = String::new();
+		for c in text.chars() {
+			let c_width = c.width().unwrap_or(0);
+			if column >= offset && column + c_width <= offset + width {
+				visible.push(c);
+			}
+			column += c_width;
+		}
+		if !visible.is_empty() {
+			spans.push(Span::styled(visible, style));
+		}
+	}
+	let marker_style = Style::default().fg(theme.background).bg(theme.accent);
+	if offset > 0 && total > 0 {
+		if let Some(first) = spans.first_mut() {
+			let rest: String = first.content.chars().skip(1).collect();
+			*first = Span::styled(rest, first.style);
+		}
+		spans.insert(0, Span::styled("«", marker_style));
+	}
+	if total > offset + width {
+		if let Some(last) = spans.last_mut() {
+			let mut rest = last.content.to_string();
+			rest.pop();
+			*last = Span::styled(rest, last.style);
+		}
+		spans.push(Span::styled("»", marker_style));
+	}
+	spans
+}
+
+/// Lays a table out in columns, shrinking the widest columns until the whole
+/// table fits into `width`.
+fn layout_table(table: Table, width: usize, theme: &Theme) -> Vec<Vec<Span<'static>>> {
+	let columns = table.rows.iter().map(|x| x.len()).max().unwrap_or(0);
+	if columns == 0 {
+		return vec![];
+	}
+	let mut widths = vec![0; columns];
+	for row in table.rows.iter() {
+		for (i, cell) in row.iter().enumerate() {
+			widths[i] = widths[i].max(cell.width());
+		}
+	}
+	let separators = (columns - 1) * 3;
+	while widths.iter().sum::<usize>() + separators > width {
+		let widest = (0..columns).max_by_key(|x| widths[*x]).unwrap_or(0);
+		if widths[widest] <= 1 {
+			break;
+		}
+		widths[widest] -= 1;
+	}
+	let separator_style = Style::default().fg(theme.dim);
+	let mut lines = vec![];
+	for (i, row) in table.rows.iter().enumerate() {
+		let style = match i < table.header_rows {
+			true => Style::default().add_modifier(Modifier::BOLD),
+			false => Style::default(),
+		};
+		let mut line = vec![];
+		for (column, column_width) in widths.iter().enumerate() {
+			if column > 0 {
+				line.push(Span::styled(" │ ", separator_style));
+			}
+			let cell = row.get(column).map(|x| x.as_str()).unwrap_or_default();
+			line.push(Span::styled(fit(cell, *column_width), style));
+		}
+		lines.push(line);
+		if i + 1 == table.header_rows {
+			let rule: Vec<String> = widths.iter().map(|x| "─".repeat(*x)).collect();
+			lines.push(vec![Span::styled(rule.join("─┼─"), separator_style)]);
+		}
+	}
+	lines
+}
+
+/// Pads or truncates `text` to exactly `width` columns.
+fn fit(text: &str, width: usize) -> String {
+	if text.width() <= width {
+		return format!("{}{}", text, " ".repeat(width - text.width()));
+	}
+	let mut fitted = String::new();
+	for c in text.chars() {
+		if fitted.width() + c.to_string().width() + 1 > width {
+			break;
+		}
+		fitted.push(c);
+	}
+	fitted.push('…');
+	format!(
+		"{}{}",
+		fitted,
+		" ".repeat(width.saturating_sub(fitted.width()))
+	)
+}
@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+use image::{DynamicImage, GenericImageView};
+use tui::layout::Rect;
+
+use crate::markdown::decode_entities;
+use crate::utils;
+
+/// How many decoded images are kept around before the cache is emptied.
+const CACHE_SIZE: usize = 128;
+
+/// Every image of a gallery post as (url, caption), in gallery order.
+pub fn gallery_images(post: &serde_json::Value) -> Vec<(String, Option<String>)> {
+	let items = post["gallery_data"]["items"]
+		.as_array()
+		.cloned()
+		.unwrap_or_default();
+	items
+		.iter()
+		.filter_map(|item| {
+			let media = &post["media_metadata"][item["media_id"].as_str()?];
+			let source = &media["s"];
+			let url = source["u"].as_str().or_else(|| source["gif"].as_str())?;
+			let caption = item["caption"].as_str().map(decode_entities);
+			Some((decode_entities(url), caption))
+		})
+		.collect()
+}
+
+/// The best image to show for a post: the linked image, the first image of a
+/// gallery, or the preview/thumbnail reddit generated for links and videos.
+pub fn post_image(post: &serde_json::Value) -> Option<String> {
+	if post["post_hint"] == "image" {
+		if let Some(url) = post["url"].as_str() {
+			return Some(decode_entities(url));
+		}
+	}
+	if let Some((url, _)) = gallery_images(post).into_iter().next() {
+		return Some(url);
+	}
+	if let Some(url) = post["preview"]["images"][0]["source"]["url"].as_str() {
+		return Some(decode_entities(url));
+	}
+	post["thumbnail"]
+		.as_str()
+		.filter(|x| x.starts_with("http"))
+		.map(decode_entities)
+}
+
+/// Image posts and galleries, the posts the image viewer steps through.
+pub fn is_image_post(post: &serde_json::Value) -> bool {
+	post["post_hint"] == "image" || post["is_gallery"] == true
+}
+
+/// The small preview reddit makes for a post. Posts without one have a word
+/// like "self", "nsfw" or "spoiler" in its place, which is returned as the error.
+pub fn post_thumbnail(post: &serde_json::Value) -> Result<String, String> {
+	let thumbnail = post["thumbnail"].as_str().unwrap_or("self");
+	if thumbnail.starts_with("http") {
+		return Ok(decode_entities(thumbnail));
+	}
+	match post["preview"]["images"][0]["resolutions"][0]["url"].as_str() {
+		Some(url) if thumbnail == "default" || thumbnail == "image" => Ok(decode_entities(url)),
+		_ => Err(thumbnail.to_string()),
+	}
+}
+
+/// Draws images on top of the tui buffer with viuer. tui knows nothing about
+/// them, so whenever what should be on screen changes the old image is wiped
+/// by forcing a full redraw before the new one is printed.
+pub struct Images {
+	pub enabled: bool,
+	cache: HashMap<String, Option<DynamicImage>>,
+	wanted: Vec<Placement>,
+	shown: Vec<Placement>,
+}
+
+/// Where an image goes and which part of it is shown. `zoom` is in percent,
+/// `pan` moves the zoomed in window in steps of a quarter of its size.
+#[derive(Clone, PartialEq)]
+struct Placement {
+	url: String,
+	area: Rect,
+	zoom: u32,
+	pan: (i32, i32),
+}
+
+impl Images {
+	pub fn new(enabled: bool) -> Images {
+		Images {
+			enabled,
+			cache: HashMap::new(),
+			wanted: vec![],
+			shown: vec![],
+		}
+	}
+
+	pub fn toggle(&mut self) {
+		self.enabled = !self.enabled;
+	}
+
+	/// Called at the start of every frame, the draw functions then ask for the
+	/// images they want with `request`.
+	pub fn begin_frame(&mut self) {
+		self.wanted.clear();
+	}
+
+	pub fn request(&mut self, url: String, area: Rect) {
+		self.request_zoomed(url, area, 100, (0, 0));
+	}
+
+	pub fn request_zoomed(&mut self, url: String, area: Rect, zoom: u32, pan: (i32, i32)) {
+		if self.enabled && area.width > 0 && area.height > 0 {
+			self.wanted.push(Placement {
+				url,
+				area,
+				zoom,
+				pan,
+			});
+		}
+	}
+
+	/// Hides every image, e.g. while a dialog is drawn over them.
+	pub fn hide(&mut self) {
+		self.wanted.clear();
+	}
+
+	/// Whether the screen needs a full redraw to get rid of old images.
+	pub fn needs_clear(&self) -> bool {
+		!self.shown.is_empty() && self.shown != self.wanted
+	}
+
+	pub fn changed(&self) -> bool {
+		self.shown != self.wanted
+	}
+
+	/// Removes images drawn with the kitty protocol, which live in their own
+	/// layer and survive a redraw of the text below them.
+	pub fn clear(&mut self) -> io::Result<()> {
+		if viuer::get_kitty_support() != viuer::KittySupport::None {
+			let mut stdout = io::stdout();
+			write!(stdout, "\x1b_Ga=d\x1b\\")?;
+			stdout.flush()?;
+		}
+		self.shown.clear();
+		Ok(())
+	}
+
+	/// Forgets the downloaded images, which were fetched with another account.
+	pub fn clear_cache(&mut self) {
+		self.cache.clear();
+	}
+
+	/// Prints the requested images, downloading the ones not seen before.
+	pub fn present(&mut self, reddit_cookie: &str) {
+		for placement in self.wanted.clone() {
+			let url = placement.url;
+			if !self.cache.contains_key(&url) {
+				if self.cache.len() >= CACHE_SIZE {
+					self.cache.clear();
+				}
+				let image = utils::get_bytes(&url, reddit_cookie)
+					.ok()
+					.and_then(|x| image::load_from_memory(&x).ok());
+				self.cache.insert(url.clone(), image);
+			}
+			if let Some(Some(image)) = self.cache.get(&url) {
+				match placement.zoom {
+					100 => print_image(image, placement.area),
+					zoom => print_image(&crop(image, zoom, placement.pan), placement.area),
+				}
+			}
+		}
+		self.shown = self.wanted.clone();
+	}
+}
+
+/// The part of `image` visible at `zoom` percent, moved from the center by `pan`.
+fn crop(image: &DynamicImage, zoom: u32, pan: (i32, i32)) -> DynamicImage {
+	let (width, height) = image.dimensions();
+	let crop_w = (width * 100 / zoom).max(1);
+	let crop_h = (height * 100 / zoom).max(1);
+	let center = |size: u32, crop: u32, steps: i32| {
+		let center = size as i64 / 2 + steps as i64 * crop as i64 / 4;
+		let half = crop as i64 / 2;
+		(center.clamp(half, size as i64 - half) - half) as u32
+	};
+	image.crop_imm(
+		center(width, crop_w, pan.0),
+		center(height, crop_h, pan.1),
+		crop_w,
+		crop_h,
+	)
+}
+
+/// Prints `image` as large as it fits into `area`, keeping its aspect ratio
+/// and centering it. Terminal cells are assumed to be twice as tall as wide.
+fn print_image(image: &DynamicImage, area: Rect) {
+	let (width, height) = image.dimensions();
+	if width == 0 || height == 0 {
+		return;
+	}
+	let mut cells_w = area.width as u32;
+	let mut cells_h = (height * cells_w / width / 2).max(1);
+	if cells_h > area.height as u32 {
+		cells_h = area.height as u32;
+		cells_w = (width * cells_h * 2 / height).clamp(1, area.width as u32);
+	}
+	let config = viuer::Config {
+		x: area.x + (area.width - cells_w as u16) / 2,
+		y: (area.y + (area.height - cells_h as u16) / 2) as i16,
+		width: Some(cells_w),
+		height: Some(cells_h),
+		restore_cursor: true,
+		..Default::default()
+	};
+	// A broken image just leaves the area empty
+	let _ = viuer::print(image, &config);
+}
@@ -0,0 +1,61 @@
+use crate::app::StatefulList;
+use crate::utils;
+
+pub const LISTINGS: [&str; 4] = ["modqueue", "reports", "spam", "unmoderated"];
+
+/// State of the Mod tab: the subreddits the user moderates and the currently
+/// open moderation listing of one of them.
+pub struct Moderation {
+	pub subreddits: StatefulList<String>,
+	pub listing: usize,
+	pub items: StatefulList<serde_json::Value>,
+	pub current_focus: usize,
+}
+
+impl Moderation {
+	pub fn new(subreddits: Vec<String>) -> Moderation {
+		Moderation {
+			subreddits: StatefulList::with_items(subreddits),
+			listing: 0,
+			items: StatefulList::with_items(vec![]),
+			current_focus: 0,
+		}
+	}
+	pub fn subreddit(&self) -> Option<&String> {
+		self.subreddits.items.get(self.subreddits.state.selected()?)
+	}
+	/// The `data` of the selected post or comment in the listing.
+	pub fn selected_item(&self) -> Option<&serde_json::Value> {
+		self.items
+			.items
+			.get(self.items.state.selected()?)
+			.map(|x| &x["data"])
+	}
+	pub fn next_listing(&mut self) {
+		self.listing = (self.listing + 1) % LISTINGS.len();
+	}
+	pub fn load_items(&mut self, reddit_cookie: &str) -> Result<(), String> {
+		let subreddit = match self.subreddit() {
+			Some(x) => x.clone(),
+			None => return Ok(()),
+		};
+		let items = utils::get_mod_listing(&subreddit, LISTINGS[self.listing], reddit_cookie)?;
+		self.items = StatefulList::with_items(items);
+		Ok(())
+	}
+	/// Drops an item that left the listing, e.g. after being approved or removed.
+	pub fn remove_item(&mut self, id: &str) {
+		self.items.items.retain(|x| x["data"]["name"] != id);
+		let selected = self.items.state.selected().unwrap_or(0);
+		self.items
+			.state
+			.select(Some(selected.min(self.items.items.len().saturating_sub(1))));
+	}
+	pub fn update_item(&mut self, id: &str, field: &str, value: serde_json::Value) {
+		for item in self.items.items.iter_mut() {
+			if item["data"]["name"] == id {
+				item["data"][field] = value.clone();
+			}
+		}
+	}
+}
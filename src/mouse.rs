@@ -0,0 +1,94 @@
+use std::time::{Duration, Instant};
+use tui::layout::Rect;
+
+/// Two clicks on the same row within this time count as a double click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Pane {
+	Tabs,
+	Feeds,
+	List,
+	Post,
+	Comments,
+	ModSubreddits,
+	ModItems,
+}
+
+/// Where the panes of the current frame ended up, so mouse events can be
+/// matched to them. The draw functions fill it in on every frame.
+#[derive(Default)]
+pub struct Regions {
+	pub tabs: Rect,
+	/// The feed tabs in the title of the post list.
+	pub feeds: Rect,
+	/// The rows of the post list, without its border and thumbnails.
+	pub list: Rect,
+	pub list_row_height: u16,
+	pub post: Rect,
+	pub comments: Rect,
+	pub mod_subreddits: Rect,
+	pub mod_items: Rect,
+}
+
+fn contains(area: Rect, column: u16, row: u16) -> bool {
+	column >= area.x && column < area.right() && row >= area.y && row < area.bottom()
+}
+
+impl Regions {
+	pub fn pane_at(&self, column: u16, row: u16) -> Option<Pane> {
+		[
+			(self.tabs, Pane::Tabs),
+			(self.feeds, Pane::Feeds),
+			(self.list, Pane::List),
+			(self.post, Pane::Post),
+			(self.comments, Pane::Comments),
+			(self.mod_subreddits, Pane::ModSubreddits),
+			(self.mod_items, Pane::ModItems),
+		]
+		.iter()
+		.find(|x| contains(x.0, column, row))
+		.map(|x| x.1)
+	}
+
+	/// The visible row of the post list at `row`, counted from the top of the list.
+	pub fn list_row(&self, row: u16) -> usize {
+		((row - self.list.y) / self.list_row_height.max(1)) as usize
+	}
+}
+
+/// The tab whose title is at `column`. Mirrors how tui's `Tabs` lays titles
+/// out: a space, the title, a space and a one cell divider.
+pub fn tab_at(titles: &[&str], area: Rect, column: u16) -> Option<usize> {
+	let mut x = area.x + 1;
+	for (i, title) in titles.iter().enumerate() {
+		let width = title.chars().count() as u16 + 2;
+		if column >= x && column < x + width {
+			return Some(i);
+		}
+		x += width + 1;
+	}
+	None
+}
+
+/// Remembers the last click to recognise double clicks.
+#[derive(Default)]
+pub struct Clicks {
+	last: Option<(Instant, u16)>,
+}
+
+impl Clicks {
+	/// Registers a click on `row` and returns whether it completes a double click.
+	pub fn click(&mut self, row: u16) -> bool {
+		let now = Instant::now();
+		let double = match self.last {
+			Some((time, last_row)) => last_row == row && now - time < DOUBLE_CLICK,
+			None => false,
+		};
+		self.last = match double {
+			true => None,
+			false => Some((now, row)),
+		};
+		double
+	}
+}
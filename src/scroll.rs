@@ -0,0 +1,108 @@
+use tui::{backend::Backend, layout::Rect, style::Style, text::Spans, widgets::Paragraph, Frame};
+use unicode_width::UnicodeWidthStr;
+
+/// How tall the text of a pane is and how much of it fits, recorded when the
+/// pane is drawn so scrolling can stop at the end of the text.
+#[derive(Clone, Copy, Default)]
+pub struct Extent {
+	/// Rows of the text once it is wrapped.
+	pub rows: u16,
+	/// Rows the pane shows.
+	pub height: u16,
+	/// Columns the widest code block line is cut off at the right by.
+	pub columns: u16,
+}
+
+impl Extent {
+	/// The scroll that shows the last row of the text at the bottom.
+	pub fn max_scroll(&self) -> u16 {
+		self.rows.saturating_sub(self.height)
+	}
+
+	/// Rows moved by a page, or half of them.
+	pub fn page(&self, half: bool) -> u16 {
+		match half {
+			true => (self.height / 2).max(1),
+			false => self.height.max(1),
+		}
+	}
+}
+
+/// Rows `line` takes when wrapped to `width` at spaces, the way `Paragraph`
+/// wraps it. Words longer than a row are broken up.
+fn wrapped_rows(line: &Spans, width: usize) -> usize {
+	let text: String = line.0.iter().map(|x| x.content.as_ref()).collect();
+	let mut rows = 1;
+	let mut used = 0;
+	for word in text.split_inclusive(' ') {
+		let length = word.trim_end_matches(' ').width();
+		if used > 0 && used + length > width {
+			rows += 1;
+			used = 0;
+		}
+		used += length;
+		while used > width {
+			rows += 1;
+			used -= width;
+		}
+		// Spaces at the end of a row are dropped
+		used = (used + word.width() - length).min(width);
+	}
+	rows
+}
+
+/// The row line `index` of `lines` starts on once they are wrapped to `width`.
+pub fn row_of(lines: &[Spans], index: usize, width: u16) -> u16 {
+	let width = width.max(1) as usize;
+	lines
+		.iter()
+		.take(index)
+		.map(|x| wrapped_rows(x, width))
+		.sum::<usize>()
+		.min(u16::MAX as usize) as u16
+}
+
+/// The extent of `lines` wrapped to the width of `area`.
+pub fn extent(lines: &[Spans], area: Rect) -> Extent {
+	Extent {
+		rows: row_of(lines, lines.len(), area.width),
+		height: area.height,
+		columns: 0,
+	}
+}
+
+/// Draws a scrollbar on the right border of `area`, a bordered pane, and how
+/// far down it is scrolled on its bottom border. Nothing is drawn when the
+/// text fits.
+pub fn draw_scrollbar<B>(f: &mut Frame<B>, area: Rect, scroll: u16, extent: Extent, style: Style)
+where
+	B: Backend,
+{
+	let track = area.height.saturating_sub(2);
+	if extent.rows <= extent.height || track == 0 || area.width < 8 {
+		return;
+	}
+	let max_scroll = extent.max_scroll() as u32;
+	let scroll = (scroll as u32).min(max_scroll);
+	let thumb = (track as u32 * extent.height as u32 / extent.rows as u32).clamp(1, track as u32);
+	let top = (track as u32 - thumb) * scroll / max_scroll;
+	let thumb_area = Rect {
+		x: area.right() - 1,
+		y: area.y + 1 + top as u16,
+		width: 1,
+		height: thumb as u16,
+	};
+	let lines: Vec<Spans> = (0..thumb).map(|_| Spans::from("┃")).collect();
+	f.render_widget(Paragraph::new(lines).style(style), thumb_area);
+	let percent = format!("{}{}{}", " ", scroll * 100 / max_scroll, "% ");
+	let width = percent.len() as u16;
+	f.render_widget(
+		Paragraph::new(percent).style(style),
+		Rect {
+			x: area.right().saturating_sub(width + 1),
+			y: area.bottom() - 1,
+			width,
+			height: 1,
+		},
+	);
+}
@@ -0,0 +1,188 @@
+use std::time::{Duration, Instant};
+use tui::{
+	backend::Backend,
+	layout::{Constraint, Direction, Layout, Rect},
+	style::{Modifier, Style},
+	text::{Span, Spans},
+	widgets::Paragraph,
+	Frame,
+};
+
+use crate::app::App;
+use crate::feeds;
+use crate::user_config;
+use crate::utils;
+
+/// How long a toast stays in the status bar.
+const TOAST_TIME: Duration = Duration::from_secs(3);
+
+struct Toast {
+	message: String,
+	shown: Instant,
+}
+
+/// What the status bar shows besides the app state it reads directly.
+pub struct Status {
+	/// Page of the feed, counting the pages loaded since the feed was opened.
+	pub page: usize,
+	/// Set while a command that may wait on reddit runs.
+	pub loading: bool,
+	toast: Option<Toast>,
+}
+
+impl Status {
+	pub fn new() -> Status {
+		Status {
+			page: 1,
+			loading: false,
+			toast: None,
+		}
+	}
+
+	/// Shows `message` in the status bar for a few seconds.
+	pub fn toast(&mut self, message: String) {
+		self.toast = Some(Toast {
+			message,
+			shown: Instant::now(),
+		});
+	}
+
+	fn current_toast(&self) -> Option<&str> {
+		self.toast
+			.as_ref()
+			.filter(|x| x.shown.elapsed() < TOAST_TIME)
+			.map(|x| x.message.as_str())
+	}
+
+	/// Time left until the toast goes away, so the screen can be redrawn then.
+	pub fn toast_timeout(&self) -> Option<Duration> {
+		self.toast
+			.as_ref()
+			.and_then(|x| TOAST_TIME.checked_sub(x.shown.elapsed()))
+	}
+}
+
+/// Where the user is: the feed, its sorts, the page and the selected post.
+fn location(app: &App, v: &[serde_json::Value]) -> Vec<Span<'static>> {
+	let dim = Style::default().fg(app.theme.dim);
+	let feed = match app.viewing_hidden {
+		true => "hidden posts".to_string(),
+		false => feeds::title(&app.input),
+	};
+	let mut spans = vec![
+		Span::styled(
+			format!("{}{}", " ", feed),
+			Style::default()
+				.fg(app.theme.tab)
+				.add_modifier(Modifier::BOLD),
+		),
+		Span::styled(" · sort ", dim),
+		Span::raw(app.sort_by.clone()),
+	];
+	if !app.sort_time.is_empty() && (app.sort_by == "top" || app.sort_by == "controversial") {
+		spans.push(Span::styled(
+			format!("{}{}{}", " (", app.sort_time, ")"),
+			dim,
+		));
+	}
+	if app.index == 1 {
+		spans.push(Span::styled(" · comments ", dim));
+		spans.push(Span::raw(app.comments_sort_by.clone()));
+	}
+	if app.feeds.len() > 1 {
+		spans.push(Span::styled(" · tab ", dim));
+		spans.push(Span::raw(format!(
+			"{}{}{}",
+			app.feed + 1,
+			"/",
+			app.feeds.len()
+		)));
+	}
+	if let Some(find) = app.find.as_ref().filter(|x| !x.query.is_empty()) {
+		spans.push(Span::styled(" · find ", dim));
+		spans.push(Span::raw(match find.count {
+			0 => "no matches".to_string(),
+			count => format!("{}{}{}", find.current + 1, "/", count),
+		}));
+	}
+	spans.push(Span::styled(" · page ", dim));
+	spans.push(Span::raw(app.status.page.to_string()));
+	if !v.is_empty() {
+		spans.push(Span::styled(" · ", dim));
+		spans.push(Span::raw(format!(
+			"{}{}{}",
+			app.items.state.selected().unwrap_or(0) + 1,
+			"/",
+			v.len()
+		)));
+	}
+	spans
+}
+
+/// The toast if there is one, otherwise the account and reddit's quota.
+fn state(app: &App) -> Vec<Span<'static>> {
+	let dim = Style::default().fg(app.theme.dim);
+	if let Some(toast) = app.status.current_toast() {
+		return vec![Span::styled(
+			format!("{}{}", toast, " "),
+			Style::default()
+				.fg(app.theme.accent)
+				.add_modifier(Modifier::BOLD),
+		)];
+	}
+	let mut spans = vec![];
+	if app.status.loading {
+		spans.push(Span::styled(
+			"loading… ",
+			Style::default().fg(app.theme.accent),
+		));
+	}
+	if let Some(limit) = utils::rate_limit() {
+		spans.push(Span::styled(
+			format!(
+				"{}{}{}{}",
+				limit.remaining as u64,
+				" requests left, reset in ",
+				limit.resets_in(),
+				"s · "
+			),
+			dim,
+		));
+	}
+	// Only said when there is more than one account to tell apart
+	if app.accounts.len() > 1 {
+		spans.push(Span::styled(
+			format!(
+				"{}{}",
+				user_config::account_name(&app.credentials.account),
+				": "
+			),
+			dim,
+		));
+	}
+	spans.push(match &app.username {
+		Some(name) => Span::styled(
+			format!("{}{}{}", "u/", name, " "),
+			Style::default().fg(app.theme.author),
+		),
+		None => Span::styled("anonymous ", dim),
+	});
+	spans
+}
+
+pub fn draw_status_bar<B>(f: &mut Frame<B>, app: &App, v: &[serde_json::Value], area: Rect)
+where
+	B: Backend,
+{
+	let right = Spans::from(state(app));
+	let parts = Layout::default()
+		.direction(Direction::Horizontal)
+		.constraints([Constraint::Min(0), Constraint::Length(right.width() as u16)].as_ref())
+		.split(area);
+	let style = app.theme.status_bar();
+	f.render_widget(
+		Paragraph::new(Spans::from(location(app, v))).style(style),
+		parts[0],
+	);
+	f.render_widget(Paragraph::new(right).style(style), parts[1]);
+}
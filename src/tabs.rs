@@ -1,177 +1,552 @@
 use tui::{
 	backend::Backend,
-	layout::{Alignment, Constraint, Direction, Layout},
-	style::{Color, Modifier, Style},
-	text::Spans,
-	widgets::{Block, Borders, List, ListItem, Paragraph, Tabs, Wrap},
+	layout::{Alignment, Constraint, Direction, Layout, Rect},
+	style::{Modifier, Style},
+	text::{Span, Spans, Text},
+	widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Tabs, Wrap},
 	Frame,
 };
 
+use crate::layout;
+use crate::markdown;
+use crate::media;
+use crate::mouse::Pane;
+use crate::post_info;
+use crate::scroll::{self, Extent};
+use crate::theme::Theme;
+
+/// Width in cells of the thumbnail column of the Home list.
+const THUMBNAIL_WIDTH: u16 = 6;
+
 pub fn draw_first_tab<B>(
 	f: &mut Frame<B>,
 	app: &mut crate::app::App,
-	v: &Vec<serde_json::Value>,
+	v: &[serde_json::Value],
 	tabs: Tabs,
+	size: Rect,
 ) where
 	B: Backend,
 {
-	let size = f.size();
 	let chunks = Layout::default()
 		.direction(Direction::Vertical)
 		.constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
 		.split(size);
 	f.render_widget(tabs, chunks[0]);
-	let items = &app.items.items;
-	let items: Vec<ListItem> = items
+	// Wide terminals get a preview of the selected post next to the list
+	if layout::is_wide(size) {
+		let (list, post) = layout::split(chunks[1], Direction::Horizontal, app.panes.list_width);
+		draw_post_list(f, app, v, list);
+		draw_post(f, app, v, post, false);
+	} else {
+		draw_post_list(f, app, v, chunks[1]);
+	}
+}
+
+fn draw_post_list<B>(
+	f: &mut Frame<B>,
+	app: &mut crate::app::App,
+	v: &[serde_json::Value],
+	area: Rect,
+) where
+	B: Backend,
+{
+	let theme = app.theme.clone();
+	// With several feeds the title shows their tabs, laid out the way
+	// `mouse::tab_at` expects
+	let list_title = match app.feeds.len() {
+		1 => Spans::from(match app.viewing_hidden {
+			true => "Hidden",
+			false => "Posts",
+		}),
+		_ => {
+			let mut spans = vec![];
+			for (i, title) in app.feed_titles().into_iter().enumerate() {
+				if i > 0 {
+					spans.push(Span::styled("│", theme.border(false)));
+				}
+				spans.push(Span::styled(
+					format!("{}{}{}", " ", title, " "),
+					match i == app.feed {
+						true => theme.selected_tab(),
+						false => Style::default().fg(theme.tab),
+					},
+				));
+			}
+			app.regions.feeds = Rect { height: 1, ..area };
+			Spans::from(spans)
+		}
+	};
+	let block = Block::default()
+		.borders(Borders::ALL)
+		.title(list_title)
+		.border_style(theme.border(false));
+	let list_area = block.inner(area);
+	f.render_widget(block, area);
+	let thumbnail_width = match app.thumbnails {
+		true => THUMBNAIL_WIDTH + 1,
+		false => 0,
+	};
+	let columns = Layout::default()
+		.direction(Direction::Horizontal)
+		.constraints([Constraint::Length(thumbnail_width), Constraint::Min(0)].as_ref())
+		.split(list_area);
+
+	// Every row has the same height, which makes it easy to work out which
+	// posts are on screen and where their thumbnails go
+	let row_height: u16 = match app.thumbnails {
+		true => 3,
+		false => 2,
+	};
+	app.regions.list = columns[1];
+	app.regions.list_row_height = row_height;
+	// The search goes through every title, not only the ones on screen
+	let mut titles: Vec<Spans> = v
+		.iter()
+		.map(|x| post_info::title_line(&x["data"], &theme))
+		.collect();
+	if let (0, Some(find)) = (app.index, &mut app.find) {
+		if let Some(i) = find.apply(Pane::List, &mut titles, &theme) {
+			app.items.state.select(Some(i));
+		}
+	}
+	let visible_rows = (list_area.height / row_height).max(1) as usize;
+	let selected = app.items.state.selected().unwrap_or(0);
+	if selected < app.list_offset {
+		app.list_offset = selected;
+	} else if selected >= app.list_offset + visible_rows {
+		app.list_offset = selected + 1 - visible_rows;
+	}
+	let visible = app
+		.items
+		.items
 		.iter()
-		.map(|i| {
-			let p = i.0.clone();
-			let mut lines = vec![Spans::from(p)];
-			let mut content = match v[i.1 as usize]["data"]["selftext"].as_str() {
-				Some(x) => x,
-				None => "Not a text post",
+		.skip(app.list_offset)
+		.take(visible_rows);
+	let mut items: Vec<ListItem> = vec![];
+	for (row, i) in visible.enumerate() {
+		let post = &v[i.1]["data"];
+		let mut lines = vec![titles[i.1].clone(), post_info::details_line(post, &theme)];
+		lines.resize(row_height as usize, Spans::default());
+		items.push(ListItem::new(lines).style(theme.base()));
+		if app.thumbnails {
+			let area = Rect {
+				x: columns[0].x,
+				y: list_area.y + row as u16 * row_height,
+				width: THUMBNAIL_WIDTH,
+				height: row_height,
 			};
-			if content == "" {
-				content = "Not a text post";
+			match media::post_thumbnail(post) {
+				Ok(url) => app.images.request(url, area),
+				Err(kind) => f.render_widget(
+					Paragraph::new(kind)
+						.style(Style::default().fg(theme.dim))
+						.alignment(Alignment::Center),
+					area,
+				),
 			}
-			lines.push(Spans::from(content));
-			ListItem::new(lines).style(Style::default().fg(Color::White).bg(Color::Black))
-		})
-		.collect();
+		}
+	}
 	let items = List::new(items)
-		.block(Block::default().borders(Borders::ALL).title("Posts"))
-		.highlight_style(
-			Style::default()
-				.bg(Color::Blue)
-				.fg(Color::Black)
-				.add_modifier(Modifier::BOLD),
-		)
+		.highlight_style(theme.highlight())
 		.highlight_symbol(">> ");
-	f.render_stateful_widget(items, chunks[1], &mut app.items.state);
+	let mut state = ListState::default();
+	state.select(selected.checked_sub(app.list_offset));
+	f.render_stateful_widget(items, columns[1], &mut state);
+	let extent = Extent {
+		rows: v.len() as u16,
+		height: visible_rows as u16,
+		columns: 0,
+	};
+	scroll::draw_scrollbar(f, area, app.list_offset as u16, extent, theme.border(false));
 }
+
 pub fn draw_second_tab<B>(
 	f: &mut Frame<B>,
 	app: &mut crate::app::App,
-	v: &Vec<serde_json::Value>,
+	v: &[serde_json::Value],
 	tabs: Tabs,
+	size: Rect,
 ) where
 	B: Backend,
 {
-	let size = f.size();
 	let chunks = Layout::default()
 		.direction(Direction::Vertical)
-		.constraints(
-			[
-				Constraint::Length(3),
-				Constraint::Percentage(20),
-				Constraint::Percentage(50),
-				Constraint::Min(0),
-			]
-			.as_ref(),
-		)
+		.constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
 		.split(size);
 	f.render_widget(tabs, chunks[0]);
-	let index = match app.items.state.selected() {
-		Some(x) => x,
-		None => 0,
-	};
-	let title = match v[index]["data"]["title"].as_str() {
-		Some(x) => x,
-		None => "Post",
-	};
-	let subreddit = match v[index]["data"]["subreddit_name_prefixed"].as_str() {
-		Some(x) => x,
-		None => "Post",
-	};
-	let widget = Block::default().title(subreddit).borders(Borders::ALL);
-	let title_paragraph = Paragraph::new(title)
-		.style(Style::default().bg(Color::Black).fg(Color::White))
+	if layout::is_wide(size) {
+		let (list, rest) = layout::split(chunks[1], Direction::Horizontal, app.panes.list_width);
+		let (post, comments) = layout::split(rest, Direction::Horizontal, app.panes.post_size);
+		draw_post_list(f, app, v, list);
+		draw_post(f, app, v, post, app.current_focus == 0);
+		draw_comments(f, app, v, comments);
+	} else {
+		let (post, comments) = layout::split(chunks[1], Direction::Vertical, app.panes.post_size);
+		draw_post(f, app, v, post, app.current_focus == 0);
+		draw_comments(f, app, v, comments);
+	}
+}
+
+/// The header with the title and details of the selected post above its
+/// image and text.
+fn draw_post<B>(
+	f: &mut Frame<B>,
+	app: &mut crate::app::App,
+	v: &[serde_json::Value],
+	area: Rect,
+	focused: bool,
+) where
+	B: Backend,
+{
+	app.regions.post = area;
+	let index = app.items.state.selected().unwrap_or_default();
+	let post = v.get(index).cloned().unwrap_or_default();
+	let subreddit = post["data"]["subreddit_name_prefixed"]
+		.as_str()
+		.unwrap_or("Post");
+	let theme = app.theme.clone();
+	let widget = Block::default()
+		.title(subreddit)
+		.borders(Borders::ALL)
+		.border_style(theme.border(false));
+	let header = vec![
+		post_info::title_line(&post["data"], &theme),
+		post_info::details_line(&post["data"], &theme),
+	];
+	// The header is as tall as its wrapped lines, up to a third of the pane
+	let inner_width = area.width.saturating_sub(2).max(1) as usize;
+	let header_rows: usize = header
+		.iter()
+		.map(|x| x.width().max(1).div_ceil(inner_width))
+		.sum();
+	let header_height = (header_rows as u16 + 2).min((area.height / 3).max(4));
+	let chunks = Layout::default()
+		.direction(Direction::Vertical)
+		.constraints([Constraint::Length(header_height), Constraint::Min(0)].as_ref())
+		.split(area);
+	let title_paragraph = Paragraph::new(header)
+		.style(theme.base())
 		.block(widget)
 		.alignment(Alignment::Left)
 		.wrap(Wrap { trim: true });
-	f.render_widget(title_paragraph, chunks[1]);
+	f.render_widget(title_paragraph, chunks[0]);
 
 	let widget2 = Block::default()
 		.title("Post")
 		.borders(Borders::ALL)
-		.border_style(Style::default().fg(match app.current_focus {
-			0 => Color::Red,
-			_ => Color::White,
-		}));
-	let data = v[index]["data"]["selftext"].clone();
-	let text;
-	let c = match data.as_str() {
-		Some(x) => x,
-		None => "Not a text post",
+		.border_style(theme.border(focused));
+	let data = post["data"]["selftext"].clone();
+	let c = data.as_str().unwrap_or("Not a text post");
+	let post_area = widget2.inner(chunks[1]);
+	f.render_widget(widget2, chunks[1]);
+	// Images take the top of the post pane, the selftext goes below them
+	let text_area = match media::post_image(&post["data"]) {
+		Some(url) if app.images.enabled => {
+			let parts = Layout::default()
+				.direction(Direction::Vertical)
+				.constraints(match c.is_empty() {
+					true => [Constraint::Percentage(100), Constraint::Min(0)],
+					false => [Constraint::Percentage(60), Constraint::Min(0)],
+				})
+				.split(post_area);
+			app.images.request(url, parts[0]);
+			parts[1]
+		}
+		_ => post_area,
 	};
-	text = vec![Spans::from(c)];
-	let paragraph = Paragraph::new(text.clone())
-		.style(Style::default().bg(Color::Black).fg(Color::White))
-		.block(widget2)
+	let (mut text, columns) = markdown::render(
+		c,
+		text_area.width,
+		&app.highlighter,
+		&theme,
+		app.code_scroll,
+	);
+	if let Some(find) = &mut app.find {
+		if let Some(line) = find.apply(Pane::Post, &mut text.lines, &theme) {
+			app.post_scroll = scroll::row_of(&text.lines, line, text_area.width).saturating_sub(2);
+		}
+	}
+	app.post_extent = scroll::extent(&text.lines, text_area);
+	app.post_extent.columns = columns;
+	app.post_scroll = app.post_scroll.min(app.post_extent.max_scroll());
+	let paragraph = Paragraph::new(text)
+		.style(theme.base())
 		.alignment(Alignment::Left)
-		.wrap(Wrap { trim: true })
+		.wrap(Wrap { trim: false })
 		.scroll((app.post_scroll, 0));
-	f.render_widget(paragraph, chunks[2]);
+	f.render_widget(paragraph, text_area);
+	scroll::draw_scrollbar(
+		f,
+		chunks[1],
+		app.post_scroll,
+		app.post_extent,
+		theme.border(focused),
+	);
+}
 
+fn draw_comments<B>(
+	f: &mut Frame<B>,
+	app: &mut crate::app::App,
+	v: &[serde_json::Value],
+	area: Rect,
+) where
+	B: Backend,
+{
+	app.regions.comments = area;
+	let index = app.items.state.selected().unwrap_or_default();
+	let post = v.get(index).cloned().unwrap_or_default();
+	let theme = app.theme.clone();
 	let widget3 = Block::default()
 		.title("Comments")
 		.borders(Borders::ALL)
-		.border_style(Style::default().fg(match app.current_focus {
-			1 => Color::Red,
-			_ => Color::White,
-		}));
-	let comment_count = app.comments[1]["data"]["children"]
-		.as_array()
-		.unwrap()
-		.len();
-	let mut comments = "".to_string();
-	for i in 0..comment_count {
-		let comment_text = match app.comments[1]["data"]["children"][i]["data"]["body"].as_str() {
-			Some(x) => x,
-			None => "",
+		.border_style(theme.border(app.current_focus == 1));
+	let mut comments = Text::default();
+	let mut columns = 0;
+	let post_author = post["data"]["author"].as_str().unwrap_or_default();
+	for (i, comment) in app.comment_list().iter().enumerate() {
+		let comment_text = comment["data"]["body"].as_str().unwrap_or_default();
+		let comment_author = comment["data"]["author"].as_str().unwrap_or_default();
+		let is_op = comment_author == post_author;
+		let mut author_style = Style::default().fg(match is_op {
+			true => theme.op,
+			false => theme.author,
+		});
+		if app.current_focus == 1 && i == app.selected_comment {
+			author_style = author_style.add_modifier(Modifier::REVERSED);
+		}
+		let mut header = vec![Span::styled(
+			format!("{}{}", "u/", comment_author),
+			author_style,
+		)];
+		if is_op {
+			header.push(Span::styled(
+				" OP",
+				Style::default().fg(theme.op).add_modifier(Modifier::BOLD),
+			));
+		}
+		let score = match comment["data"]["score_hidden"] == true {
+			true => "•".to_string(),
+			false => {
+				post_info::compact_number(comment["data"]["score"].as_i64().unwrap_or_default())
+			}
 		};
-		let comment_author = app.comments[1]["data"]["children"][i]["data"]["author"]
-			.as_str()
-			.unwrap_or_default();
-		let text = format!(
-			"{}{}{}{}{}",
-			"u/", &comment_author, " | ", comment_text, "\n"
+		header.push(Span::styled(" · ", Style::default().fg(theme.dim)));
+		header.push(Span::styled(
+			format!("{}{}", "↑", score),
+			Style::default().fg(theme.score),
+		));
+		comments.lines.push(Spans::from(header));
+		let (text, overflow) = markdown::render(
+			comment_text,
+			area.width.saturating_sub(2),
+			&app.highlighter,
+			&theme,
+			app.code_scroll,
 		);
-		comments = format!("{}{}", comments, text);
+		comments.extend(text);
+		columns = overflow.max(columns);
+		comments.lines.push(Spans::default());
+	}
+	let inner = widget3.inner(area);
+	if let Some(find) = &mut app.find {
+		if let Some(line) = find.apply(Pane::Comments, &mut comments.lines, &theme) {
+			app.comment_scroll =
+				scroll::row_of(&comments.lines, line, inner.width).saturating_sub(2);
+		}
 	}
+	app.comment_extent = scroll::extent(&comments.lines, inner);
+	app.comment_extent.columns = columns;
+	app.comment_scroll = app.comment_scroll.min(app.comment_extent.max_scroll());
 	let comment_paragraph = Paragraph::new(comments)
-		.style(Style::default().bg(Color::Black).fg(Color::White))
+		.style(theme.base())
 		.block(widget3)
 		.alignment(Alignment::Left)
-		.wrap(Wrap { trim: true })
+		.wrap(Wrap { trim: false })
 		.scroll((app.comment_scroll, 0));
-	f.render_widget(comment_paragraph, chunks[3]);
+	f.render_widget(comment_paragraph, area);
+	scroll::draw_scrollbar(
+		f,
+		area,
+		app.comment_scroll,
+		app.comment_extent,
+		theme.border(app.current_focus == 1),
+	);
 }
 
-pub fn draw_third_tab<B>(f: &mut Frame<B>, app: &mut crate::app::App, tabs: Tabs)
+pub fn draw_third_tab<B>(f: &mut Frame<B>, app: &mut crate::app::App, tabs: Tabs, size: Rect)
 where
 	B: Backend,
 {
-	let size = f.size();
 	let chunks = Layout::default()
 		.direction(Direction::Vertical)
 		.constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
 		.split(size);
 	f.render_widget(tabs, chunks[0]);
-	let second_chunk = Layout::default()
-		.direction(Direction::Vertical)
-		.vertical_margin(13)
-		.horizontal_margin(25)
-		.constraints([Constraint::Min(0)].as_ref())
-		.split(chunks[1]);
+	let search_area = layout::centered(60.max(chunks[1].width / 2), 3, chunks[1]);
 	let widget = Block::default()
 		.title("Search")
 		.borders(Borders::ALL)
-		.title_alignment(Alignment::Center);
+		.title_alignment(Alignment::Center)
+		.border_style(app.theme.border(true));
 	let title_paragraph = Paragraph::new(app.input.clone())
-		.style(Style::default().bg(Color::White).fg(Color::Black))
+		.style(app.theme.base())
 		.block(widget)
 		.alignment(Alignment::Center)
 		.wrap(Wrap { trim: true });
-	f.render_widget(title_paragraph, second_chunk[0]);
+	f.render_widget(title_paragraph, search_area);
+}
+
+pub fn draw_fourth_tab<B>(f: &mut Frame<B>, app: &mut crate::app::App, tabs: Tabs, size: Rect)
+where
+	B: Backend,
+{
+	let chunks = Layout::default()
+		.direction(Direction::Vertical)
+		.constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+		.split(size);
+	f.render_widget(tabs, chunks[0]);
+	let theme = &app.theme;
+	let moderation = match &mut app.moderation {
+		Some(x) => x,
+		None => return,
+	};
+	let columns = Layout::default()
+		.direction(Direction::Horizontal)
+		.constraints(
+			[
+				Constraint::Length(25.min(chunks[1].width / 3)),
+				Constraint::Min(0),
+			]
+			.as_ref(),
+		)
+		.split(chunks[1]);
+	let focus_style = |focus: usize| theme.border(moderation.current_focus == focus);
+	let highlight_style = theme.highlight();
+
+	let subreddits: Vec<ListItem> = moderation
+		.subreddits
+		.items
+		.iter()
+		.map(|x| ListItem::new(format!("{}{}", "r/", x)))
+		.collect();
+	let subreddits = List::new(subreddits)
+		.block(
+			Block::default()
+				.borders(Borders::ALL)
+				.title("Moderating")
+				.border_style(focus_style(0)),
+		)
+		.style(theme.base())
+		.highlight_style(highlight_style);
+	f.render_stateful_widget(subreddits, columns[0], &mut moderation.subreddits.state);
+	app.regions.mod_subreddits = columns[0];
+
+	let right = Layout::default()
+		.direction(Direction::Vertical)
+		.constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+		.split(columns[1]);
+	let listings = Tabs::new(
+		crate::moderation::LISTINGS
+			.iter()
+			.map(|x| Spans::from(*x))
+			.collect(),
+	)
+	.block(
+		Block::default()
+			.borders(Borders::ALL)
+			.title("Listing")
+			.border_style(theme.border(false)),
+	)
+	.select(moderation.listing)
+	.style(Style::default().fg(theme.tab))
+	.highlight_style(theme.selected_tab());
+	f.render_widget(listings, right[0]);
+
+	let items: Vec<ListItem> = moderation
+		.items
+		.items
+		.iter()
+		.map(|x| ListItem::new(mod_item_lines(&x["data"], x["kind"] == "t3", theme)))
+		.collect();
+	let items = List::new(items)
+		.block(
+			Block::default()
+				.borders(Borders::ALL)
+				.title("Items")
+				.border_style(focus_style(1)),
+		)
+		.style(theme.base())
+		.highlight_style(highlight_style)
+		.highlight_symbol(">> ");
+	f.render_stateful_widget(items, right[1], &mut moderation.items.state);
+	app.regions.mod_items = right[1];
+}
+
+fn mod_item_lines(item: &serde_json::Value, is_post: bool, theme: &Theme) -> Vec<Spans<'static>> {
+	let mut header = vec![
+		Span::styled(
+			match is_post {
+				true => "[post] ",
+				false => "[comment] ",
+			},
+			Style::default().fg(theme.accent),
+		),
+		Span::raw(format!(
+			"{}{}{}",
+			item["subreddit_name_prefixed"].as_str().unwrap_or_default(),
+			" u/",
+			item["author"].as_str().unwrap_or_default()
+		)),
+	];
+	for (field, label) in [("locked", " locked"), ("stickied", " stickied")] {
+		if item[field] == true {
+			header.push(Span::styled(label, Style::default().fg(theme.quote)));
+		}
+	}
+	if !item["distinguished"].is_null() {
+		header.push(Span::styled(
+			" distinguished",
+			Style::default().fg(theme.quote),
+		));
+	}
+	let content = match is_post {
+		true => item["title"].as_str(),
+		false => item["body"].as_str(),
+	};
+	let mut lines = vec![
+		Spans::from(header),
+		Spans::from(
+			content
+				.unwrap_or_default()
+				.lines()
+				.next()
+				.unwrap_or_default()
+				.to_string(),
+		),
+	];
+	// user_reports are [reason, count] pairs, mod_reports are [reason, moderator]
+	let mut reports: Vec<String> = vec![];
+	for report in item["user_reports"].as_array().cloned().unwrap_or_default() {
+		reports.push(format!(
+			"{}{}{}",
+			report[0].as_str().unwrap_or("other"),
+			" x",
+			report[1]
+		));
+	}
+	for report in item["mod_reports"].as_array().cloned().unwrap_or_default() {
+		reports.push(format!(
+			"{}{}{}",
+			report[0].as_str().unwrap_or("other"),
+			" by u/",
+			report[1].as_str().unwrap_or_default()
+		));
+	}
+	if !reports.is_empty() {
+		lines.push(Spans::from(Span::styled(
+			format!("{}{}", "reports: ", reports.join(", ")),
+			Style::default().fg(theme.warning),
+		)));
+	}
+	lines
 }
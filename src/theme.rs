@@ -0,0 +1,440 @@
+use std::collections::HashMap;
+use tui::style::{Color, Modifier, Style};
+
+pub const DEFAULT_THEME: &str = "dark";
+
+/// How many colours the terminal can show.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ColorSupport {
+	TrueColor,
+	Ansi256,
+	Ansi16,
+	/// `NO_COLOR` is set: everything is drawn in the terminal's own colours
+	/// and only bold, reversed etc. are used.
+	None,
+}
+
+/// Values of the `color_mode` option.
+pub const COLOR_MODES: [&str; 6] = ["auto", "truecolor", "24bit", "256", "16", "none"];
+
+impl ColorSupport {
+	/// Reads `mode` from the config. `auto` honours `NO_COLOR` and otherwise
+	/// guesses from `COLORTERM` and `TERM`.
+	pub fn detect(mode: &str) -> ColorSupport {
+		match mode {
+			"truecolor" | "24bit" => return ColorSupport::TrueColor,
+			"256" => return ColorSupport::Ansi256,
+			"16" => return ColorSupport::Ansi16,
+			"none" => return ColorSupport::None,
+			_ => {}
+		}
+		if std::env::var("NO_COLOR").map(|x| !x.is_empty()) == Ok(true) {
+			return ColorSupport::None;
+		}
+		let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+		if colorterm == "truecolor" || colorterm == "24bit" {
+			return ColorSupport::TrueColor;
+		}
+		match std::env::var("TERM").unwrap_or_default() {
+			x if x.contains("256color") || x.contains("kitty") => ColorSupport::Ansi256,
+			_ => ColorSupport::Ansi16,
+		}
+	}
+}
+
+/// The colours of every part of the interface, loaded from one of the bundled
+/// themes with the `colors` of the config applied on top.
+#[derive(Clone)]
+pub struct Theme {
+	pub background: Color,
+	pub text: Color,
+	/// Secondary text like post details and separators.
+	pub dim: Color,
+	pub border: Color,
+	pub focus_border: Color,
+	pub highlight: Color,
+	pub highlight_text: Color,
+	pub tab: Color,
+	/// Tab hotkeys, link hints, headings and other things that should stand out.
+	pub accent: Color,
+	pub author: Color,
+	pub op: Color,
+	pub score: Color,
+	pub flair: Color,
+	pub link: Color,
+	pub quote: Color,
+	pub code: Color,
+	pub code_background: Color,
+	/// Reports, NSFW badges and errors.
+	pub warning: Color,
+	pub support: ColorSupport,
+}
+
+fn dark() -> Theme {
+	Theme {
+		background: Color::Black,
+		text: Color::White,
+		dim: Color::DarkGray,
+		border: Color::White,
+		focus_border: Color::Red,
+		highlight: Color::Blue,
+		highlight_text: Color::Black,
+		tab: Color::Cyan,
+		accent: Color::Yellow,
+		author: Color::Green,
+		op: Color::LightBlue,
+		score: Color::Yellow,
+		flair: Color::Magenta,
+		link: Color::Blue,
+		quote: Color::Green,
+		code: Color::Yellow,
+		code_background: Color::DarkGray,
+		warning: Color::Red,
+		support: ColorSupport::TrueColor,
+	}
+}
+
+fn light() -> Theme {
+	Theme {
+		background: Color::Rgb(250, 250, 250),
+		text: Color::Rgb(30, 30, 30),
+		dim: Color::Rgb(120, 120, 120),
+		border: Color::Rgb(160, 160, 160),
+		focus_border: Color::Rgb(255, 69, 0),
+		highlight: Color::Rgb(200, 220, 255),
+		highlight_text: Color::Rgb(0, 0, 0),
+		tab: Color::Rgb(0, 100, 160),
+		accent: Color::Rgb(255, 69, 0),
+		author: Color::Rgb(0, 120, 60),
+		op: Color::Rgb(0, 90, 200),
+		score: Color::Rgb(200, 80, 0),
+		flair: Color::Rgb(140, 40, 160),
+		link: Color::Rgb(0, 80, 200),
+		quote: Color::Rgb(80, 120, 80),
+		code: Color::Rgb(160, 20, 60),
+		code_background: Color::Rgb(230, 230, 230),
+		warning: Color::Rgb(200, 0, 0),
+		support: ColorSupport::TrueColor,
+	}
+}
+
+fn high_contrast() -> Theme {
+	Theme {
+		background: Color::Black,
+		text: Color::White,
+		dim: Color::Gray,
+		border: Color::White,
+		focus_border: Color::LightYellow,
+		highlight: Color::White,
+		highlight_text: Color::Black,
+		tab: Color::White,
+		accent: Color::LightYellow,
+		author: Color::LightGreen,
+		op: Color::LightCyan,
+		score: Color::LightYellow,
+		flair: Color::LightMagenta,
+		link: Color::LightCyan,
+		quote: Color::LightGreen,
+		code: Color::White,
+		code_background: Color::Black,
+		warning: Color::LightRed,
+		support: ColorSupport::TrueColor,
+	}
+}
+
+/// Names of the bundled themes.
+pub const THEMES: [&str; 3] = ["dark", "light", "high-contrast"];
+
+/// Parses a colour name like `red` or `lightblue`, a `#rrggbb` hex code or an
+/// index into the 256 colour palette.
+pub fn parse_color(text: &str) -> Option<Color> {
+	let text = text.trim().to_lowercase();
+	if let Some(hex) = text.strip_prefix('#') {
+		if hex.len() != 6 {
+			return None;
+		}
+		let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+		return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+	}
+	if let Ok(index) = text.parse::<u8>() {
+		return Some(Color::Indexed(index));
+	}
+	Some(match text.replace(['_', '-', ' '], "").as_str() {
+		"reset" | "default" => Color::Reset,
+		"black" => Color::Black,
+		"red" => Color::Red,
+		"green" => Color::Green,
+		"yellow" => Color::Yellow,
+		"blue" => Color::Blue,
+		"magenta" => Color::Magenta,
+		"cyan" => Color::Cyan,
+		"gray" | "grey" => Color::Gray,
+		"darkgray" | "darkgrey" => Color::DarkGray,
+		"lightred" => Color::LightRed,
+		"lightgreen" => Color::LightGreen,
+		"lightyellow" => Color::LightYellow,
+		"lightblue" => Color::LightBlue,
+		"lightmagenta" => Color::LightMagenta,
+		"lightcyan" => Color::LightCyan,
+		"white" => Color::White,
+		_ => return None,
+	})
+}
+
+/// The 16 standard colours with the RGB values xterm uses for them.
+const ANSI_16: [(Color, (u8, u8, u8)); 16] = [
+	(Color::Black, (0, 0, 0)),
+	(Color::Red, (205, 0, 0)),
+	(Color::Green, (0, 205, 0)),
+	(Color::Yellow, (205, 205, 0)),
+	(Color::Blue, (0, 0, 238)),
+	(Color::Magenta, (205, 0, 205)),
+	(Color::Cyan, (0, 205, 205)),
+	(Color::Gray, (229, 229, 229)),
+	(Color::DarkGray, (127, 127, 127)),
+	(Color::LightRed, (255, 0, 0)),
+	(Color::LightGreen, (0, 255, 0)),
+	(Color::LightYellow, (255, 255, 0)),
+	(Color::LightBlue, (92, 92, 255)),
+	(Color::LightMagenta, (255, 0, 255)),
+	(Color::LightCyan, (0, 255, 255)),
+	(Color::White, (255, 255, 255)),
+];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+	let d = |x: u8, y: u8| (x as i32 - y as i32).pow(2) as u32;
+	d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+}
+
+/// The RGB value of a colour of the 256 colour palette.
+fn indexed_rgb(index: u8) -> (u8, u8, u8) {
+	match index {
+		0..=15 => ANSI_16[index as usize].1,
+		16..=231 => {
+			let i = index - 16;
+			(
+				CUBE_LEVELS[(i / 36) as usize],
+				CUBE_LEVELS[(i / 6 % 6) as usize],
+				CUBE_LEVELS[(i % 6) as usize],
+			)
+		}
+		_ => {
+			let level = 8 + (index - 232) * 10;
+			(level, level, level)
+		}
+	}
+}
+
+/// The closest colour of the 6x6x6 cube or the grey ramp of the 256 colour palette.
+fn to_256(rgb: (u8, u8, u8)) -> Color {
+	let nearest = |x: u8| {
+		(0..6)
+			.min_by_key(|i| (CUBE_LEVELS[*i] as i32 - x as i32).abs())
+			.unwrap_or(0)
+	};
+	let cube = 16 + 36 * nearest(rgb.0) + 6 * nearest(rgb.1) + nearest(rgb.2);
+	let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+	let grey = 232 + ((average.saturating_sub(8) + 5) / 10).min(23) as usize;
+	let index =
+		match distance(rgb, indexed_rgb(grey as u8)) < distance(rgb, indexed_rgb(cube as u8)) {
+			true => grey,
+			false => cube,
+		};
+	Color::Indexed(index as u8)
+}
+
+fn to_16(rgb: (u8, u8, u8)) -> Color {
+	ANSI_16
+		.iter()
+		.min_by_key(|x| distance(rgb, x.1))
+		.map(|x| x.0)
+		.unwrap_or(Color::Reset)
+}
+
+impl Theme {
+	/// Loads the bundled theme `name`, falling back to the dark one, and
+	/// applies the colour overrides from the config. Unknown names and colours
+	/// in the overrides are returned as errors but don't stop the rest.
+	pub fn load(
+		name: &str,
+		overrides: &HashMap<String, String>,
+		support: ColorSupport,
+	) -> (Theme, Vec<String>) {
+		let mut errors = vec![];
+		let mut theme = match name {
+			"dark" => dark(),
+			"light" => light(),
+			"high-contrast" => high_contrast(),
+			_ => {
+				errors.push(format!(
+					"{}{}{}{}",
+					"Unknown theme: ",
+					name,
+					", choose one of ",
+					THEMES.join(", ")
+				));
+				dark()
+			}
+		};
+		for (key, value) in overrides {
+			match parse_color(value) {
+				Some(color) => {
+					if let Err(err) = theme.set(key, color) {
+						errors.push(err);
+					}
+				}
+				None => errors.push(format!("{}{}{}{}", "Invalid colour for ", key, ": ", value)),
+			}
+		}
+		theme.support = support;
+		theme.adapt_all();
+		(theme, errors)
+	}
+
+	fn set(&mut self, name: &str, color: Color) -> Result<(), String> {
+		*match name {
+			"background" => &mut self.background,
+			"text" => &mut self.text,
+			"dim" => &mut self.dim,
+			"border" => &mut self.border,
+			"focus_border" => &mut self.focus_border,
+			"highlight" => &mut self.highlight,
+			"highlight_text" => &mut self.highlight_text,
+			"tab" => &mut self.tab,
+			"accent" => &mut self.accent,
+			"author" => &mut self.author,
+			"op" => &mut self.op,
+			"score" => &mut self.score,
+			"flair" => &mut self.flair,
+			"link" => &mut self.link,
+			"quote" => &mut self.quote,
+			"code" => &mut self.code,
+			"code_background" => &mut self.code_background,
+			"warning" => &mut self.warning,
+			_ => return Err(format!("{}{}", "Unknown theme colour: ", name)),
+		} = color;
+		Ok(())
+	}
+
+	fn adapt_all(&mut self) {
+		for color in [
+			&mut self.background,
+			&mut self.text,
+			&mut self.dim,
+			&mut self.border,
+			&mut self.focus_border,
+			&mut self.highlight,
+			&mut self.highlight_text,
+			&mut self.tab,
+			&mut self.accent,
+			&mut self.author,
+			&mut self.op,
+			&mut self.score,
+			&mut self.flair,
+			&mut self.link,
+			&mut self.quote,
+			&mut self.code,
+			&mut self.code_background,
+			&mut self.warning,
+		] {
+			*color = adapt(*color, self.support);
+		}
+	}
+
+	/// Turns any colour into one the terminal can show.
+	pub fn adapt(&self, color: Color) -> Color {
+		adapt(color, self.support)
+	}
+
+	/// `style` with its colours made to fit the terminal.
+	pub fn adapt_style(&self, style: Style) -> Style {
+		Style {
+			fg: style.fg.map(|x| self.adapt(x)),
+			bg: style.bg.map(|x| self.adapt(x)),
+			..style
+		}
+	}
+
+	/// Plain text on the background.
+	pub fn base(&self) -> Style {
+		Style::default().fg(self.text).bg(self.background)
+	}
+
+	/// The selected row of a list. Without colours it is shown reversed.
+	pub fn highlight(&self) -> Style {
+		match self.support {
+			ColorSupport::None => Style::default().add_modifier(Modifier::REVERSED),
+			_ => Style::default()
+				.fg(self.highlight_text)
+				.bg(self.highlight)
+				.add_modifier(Modifier::BOLD),
+		}
+	}
+
+	/// The border of a pane, which is bold when it has focus so it still stands
+	/// out without colours.
+	pub fn border(&self, focused: bool) -> Style {
+		match focused {
+			true => Style::default()
+				.fg(self.focus_border)
+				.add_modifier(Modifier::BOLD),
+			false => Style::default().fg(self.border),
+		}
+	}
+
+	/// The selected tab.
+	pub fn selected_tab(&self) -> Style {
+		match self.support {
+			ColorSupport::None => {
+				Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD)
+			}
+			_ => Style::default()
+				.fg(self.background)
+				.bg(self.text)
+				.add_modifier(Modifier::BOLD),
+		}
+	}
+
+	pub fn inline_code(&self) -> Style {
+		Style::default().fg(self.code).bg(self.code_background)
+	}
+
+	/// A match of the in-view search, the current one standing out.
+	pub fn find_match(&self, current: bool) -> Style {
+		match (self.support, current) {
+			(ColorSupport::None, true) => Style::default().add_modifier(Modifier::REVERSED),
+			(ColorSupport::None, false) => Style::default().add_modifier(Modifier::UNDERLINED),
+			(_, true) => Style::default()
+				.fg(self.background)
+				.bg(self.accent)
+				.add_modifier(Modifier::BOLD),
+			(_, false) => Style::default().fg(self.background).bg(self.warning),
+		}
+	}
+
+	/// The status bar at the bottom. Without colours it is shown reversed.
+	pub fn status_bar(&self) -> Style {
+		match self.support {
+			ColorSupport::None => Style::default().add_modifier(Modifier::REVERSED),
+			_ => Style::default().fg(self.text).bg(self.code_background),
+		}
+	}
+}
+
+fn adapt(color: Color, support: ColorSupport) -> Color {
+	let rgb = match color {
+		Color::Rgb(r, g, b) => (r, g, b),
+		Color::Indexed(x) => indexed_rgb(x),
+		_ if support == ColorSupport::None => return Color::Reset,
+		x => return x,
+	};
+	match (support, color) {
+		(ColorSupport::None, _) => Color::Reset,
+		(ColorSupport::TrueColor, x) => x,
+		(ColorSupport::Ansi256, Color::Indexed(x)) => Color::Indexed(x),
+		(ColorSupport::Ansi256, _) => to_256(rgb),
+		(ColorSupport::Ansi16, _) => to_16(rgb),
+	}
+}
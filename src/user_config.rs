@@ -1,57 +1,647 @@
 use serde::{Deserialize, Serialize};
-use std::{fs, path::Path};
+use std::{
+	collections::{BTreeMap, HashMap},
+	fs,
+	path::{Path, PathBuf},
+	sync::Mutex,
+};
+use toml_edit::{DocumentMut, Item, Table};
 
+use crate::clipboard;
+use crate::command_line::{SORTS, TIMES};
+use crate::credentials;
+use crate::highlight;
+use crate::keymap;
+use crate::layout::PaneSizes;
+use crate::opener::{self, Opener};
+use crate::theme;
+
+/// The config file `rusddit config init` writes, with every setting at its
+/// default and explained.
+pub const DEFAULT_CONFIG: &str = include_str!("default_config.toml");
+
+/// How the account without a name, whose tabs and cookie are at the top of
+/// the config and credentials files, is called.
+pub const DEFAULT_ACCOUNT: &str = "default";
+
+/// The config file given with `--config`, if any.
+static CONFIG_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// The settings of `config.toml`. See `default_config.toml` for what they do.
 #[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct UserConfig {
+	/// Feed tabs of the default account.
+	#[serde(default)]
 	pub tabs: Vec<String>,
+	/// Feed shown when there are no tabs, empty for the front page.
+	#[serde(default)]
+	pub default_feed: String,
+	/// The cookie of earlier versions, only read to move it to the
+	/// credentials file.
+	#[serde(default, skip_serializing_if = "String::is_empty")]
 	pub cookie: String,
+	/// Capture the mouse. Turn it off to select text with the terminal instead.
+	#[serde(default = "default_mouse")]
+	pub mouse: bool,
+	/// Ask before the quit key quits.
+	#[serde(default)]
+	pub confirm_quit: bool,
+	/// How text is copied: `auto`, `osc52` or `native`.
+	#[serde(default = "default_clipboard")]
+	pub clipboard: String,
+	/// One of the bundled themes: `dark`, `light` or `high-contrast`.
+	#[serde(default = "default_theme")]
+	pub theme: String,
+	/// `auto`, `truecolor`, `256`, `16` or `none`.
+	#[serde(default = "default_color_mode")]
+	pub color_mode: String,
+	/// Name of the bundled syntect theme used for code blocks.
+	#[serde(default = "default_code_theme")]
+	pub code_theme: String,
+	/// The key preset: `default`, `vim` or `emacs`.
+	#[serde(default = "default_keymap")]
+	pub keymap: String,
+	/// Account rusddit starts with, empty for the default one.
+	#[serde(default)]
+	pub account: String,
+	/// Named accounts, each with its own tabs and cookie.
+	#[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+	pub accounts: BTreeMap<String, Account>,
+	#[serde(default)]
+	pub sort: Sorts,
+	#[serde(default)]
+	pub filters: Filters,
+	#[serde(default)]
+	pub network: Network,
+	#[serde(default)]
+	pub media: Media,
+	/// Colours that replace the theme's, by name, e.g. `focus_border = "#ff4500"`.
+	#[serde(default)]
+	pub colors: HashMap<String, String>,
+	#[serde(default)]
+	pub panes: PaneSizes,
+	/// Bindings on top of the preset by context, e.g. `post = { J = "next_post" }`.
+	#[serde(default)]
+	pub keys: HashMap<String, HashMap<String, String>>,
+	/// Programs that open post links, tried in order.
+	#[serde(default = "opener::default_openers")]
+	pub openers: Vec<Opener>,
+	/// Problems with the config file and notes about it, shown on startup.
+	#[serde(skip)]
+	pub messages: Vec<String>,
+}
+
+/// What the config keeps of a named account. Its cookie is in the
+/// credentials file.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Account {
+	pub tabs: Vec<String>,
+}
+
+/// Sorts new feeds and posts start with.
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Sorts {
+	pub posts: String,
+	/// Time range of the top and controversial sorts, empty for reddit's default.
+	pub time: String,
+	pub comments: String,
+}
+
+impl Default for Sorts {
+	fn default() -> Sorts {
+		Sorts {
+			posts: "hot".to_string(),
+			time: String::new(),
+			comments: "best".to_string(),
+		}
+	}
+}
+
+/// Posts that are left out of feeds.
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct Filters {
+	/// Subreddits, with or without `r/`.
+	pub subreddits: Vec<String>,
+	/// Words of titles, matched ignoring case.
+	pub keywords: Vec<String>,
+	pub hide_nsfw: bool,
+}
+
+impl Filters {
+	/// Whether `post` stays in the feed.
+	pub fn allows(&self, post: &serde_json::Value) -> bool {
+		let data = &post["data"];
+		let subreddit = data["subreddit"].as_str().unwrap_or_default();
+		let title = data["title"].as_str().unwrap_or_default().to_lowercase();
+		!(self
+			.subreddits
+			.iter()
+			.any(|x| x.trim_start_matches("r/").eq_ignore_ascii_case(subreddit))
+			|| self
+				.keywords
+				.iter()
+				.any(|x| title.contains(&x.to_lowercase()))
+			|| (self.hide_nsfw && data["over_18"] == true))
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Network {
+	/// Seconds to wait for a response.
+	pub timeout: u64,
+	/// Empty to send none.
+	pub user_agent: String,
+}
+
+impl Default for Network {
+	fn default() -> Network {
+		Network {
+			timeout: 30,
+			user_agent: String::new(),
+		}
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(default, deny_unknown_fields)]
+pub struct Media {
+	pub images: bool,
+	/// Show a column of post thumbnails in the Home list.
+	pub thumbnails: bool,
+}
+
+impl Default for Media {
+	fn default() -> Media {
+		Media {
+			images: true,
+			thumbnails: false,
+		}
+	}
+}
+
+/// The JSON config of earlier versions, read once to move it to the TOML file.
+#[derive(Deserialize)]
+struct LegacyConfig {
+	#[serde(default)]
+	tabs: Vec<String>,
+	#[serde(default)]
+	cookie: String,
+	#[serde(default = "default_code_theme")]
+	code_theme: String,
+	#[serde(default)]
+	thumbnails: bool,
+	#[serde(default = "opener::default_openers")]
+	openers: Vec<Opener>,
+	#[serde(default = "default_theme")]
+	theme: String,
+	#[serde(default)]
+	colors: HashMap<String, String>,
+	#[serde(default = "default_color_mode")]
+	color_mode: String,
+	#[serde(default)]
+	panes: PaneSizes,
+	#[serde(default = "default_mouse")]
+	mouse: bool,
+	#[serde(default = "default_keymap")]
+	keymap: String,
+	#[serde(default)]
+	keys: HashMap<String, HashMap<String, String>>,
+	#[serde(default)]
+	confirm_quit: bool,
+	#[serde(default = "default_clipboard")]
+	clipboard: String,
+}
+
+fn default_code_theme() -> String {
+	highlight::DEFAULT_THEME.to_string()
+}
+
+fn default_theme() -> String {
+	theme::DEFAULT_THEME.to_string()
+}
+
+fn default_color_mode() -> String {
+	"auto".to_string()
+}
+
+fn default_mouse() -> bool {
+	true
+}
+
+fn default_clipboard() -> String {
+	clipboard::DEFAULT_BACKEND.to_string()
+}
+
+fn default_keymap() -> String {
+	keymap::DEFAULT_PRESET.to_string()
+}
+
+/// The name `account` is shown with.
+pub fn account_name(account: &str) -> &str {
+	match account {
+		"" => DEFAULT_ACCOUNT,
+		x => x,
+	}
+}
+
+/// The account called `name`, the default one being empty.
+pub fn account_key(name: &str) -> &str {
+	match name {
+		DEFAULT_ACCOUNT => "",
+		x => x,
+	}
+}
+
+/// Reads the config from `path` instead of the usual place, for `--config`.
+pub fn set_config_path(path: PathBuf) {
+	*CONFIG_PATH.lock().unwrap() = Some(path);
+}
+
+/// `$XDG_CONFIG_HOME/rusddit`, or `~/.config/rusddit` without it.
+pub fn config_dir() -> PathBuf {
+	match std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from) {
+		Some(x) if x.is_absolute() => x.join("rusddit"),
+		_ => Path::join(
+			home::home_dir().expect("what").as_path(),
+			Path::new(".config/rusddit"),
+		),
+	}
+}
+
+pub fn config_path() -> PathBuf {
+	match CONFIG_PATH.lock().unwrap().clone() {
+		Some(x) => x,
+		None => config_dir().join("config.toml"),
+	}
+}
+
+/// Where earlier versions kept their JSON config.
+fn legacy_path() -> PathBuf {
+	Path::join(
+		home::home_dir().expect("what").as_path(),
+		Path::new(".config/rusddit/config.txt"),
+	)
 }
+
+/// The line of `text` that sets `key` in `[section]`, counted from 1.
+fn line_of(text: &str, section: &str, key: &str) -> Option<usize> {
+	let mut current = "";
+	for (i, line) in text.lines().enumerate() {
+		let line = line.trim();
+		if line.starts_with('[') {
+			current = line.trim_matches(|x| x == '[' || x == ']').trim();
+		} else if current == section
+			&& line.starts_with(key)
+			&& line[key.len()..].trim_start().starts_with('=')
+		{
+			return Some(i + 1);
+		}
+	}
+	None
+}
+
+/// Where `key` of `[section]` is set in the config, with its line if it can be found.
+fn place(text: &str, section: &str, key: &str) -> String {
+	match line_of(text, section, key) {
+		Some(line) => format!("{}{}{}", config_path().display(), " line ", line),
+		None => config_path().display().to_string(),
+	}
+}
+
+/// Puts `item` at `key` of `table`. Tables are merged key by key and values
+/// replaced in place, so the comments around them are kept.
+fn merge(table: &mut Table, key: &str, item: Item) {
+	match (table.get_mut(key), item) {
+		(Some(Item::Table(old)), Item::Table(new)) => {
+			for (key, item) in new {
+				merge(old, &key, item);
+			}
+		}
+		(Some(Item::Value(old)), Item::Value(mut new)) => {
+			*new.decor_mut() = old.decor().clone();
+			*old = new;
+		}
+		(Some(old), item) => *old = item,
+		(None, item) => {
+			table.insert(key, item);
+		}
+	}
+}
+
+/// `value` as a TOML item. Structs become tables, and so do the structs in
+/// them, the way the sections of the config file are written.
+fn to_item<T: Serialize>(value: &T) -> Item {
+	match value.serialize(toml_edit::ser::ValueSerializer::new()) {
+		Ok(toml_edit::Value::InlineTable(x)) => {
+			let mut table = x.into_table();
+			for (_, item) in table.iter_mut() {
+				if let Item::Value(toml_edit::Value::InlineTable(x)) = item {
+					*item = Item::Table(std::mem::take(x).into_table());
+				}
+			}
+			Item::Table(table)
+		}
+		Ok(x) => Item::Value(x),
+		Err(_) => Item::None,
+	}
+}
+
+/// Changes the config file with `change`, keeping the rest of it as the user
+/// wrote it. Without a file the commented defaults are written first.
+fn edit(change: impl FnOnce(&mut Table)) -> Result<(), String> {
+	let path = config_path();
+	let text = fs::read_to_string(&path).unwrap_or_else(|_| DEFAULT_CONFIG.to_string());
+	let mut document = text.parse::<DocumentMut>().map_err(|err| {
+		format!(
+			"{}{}{}{}",
+			"Couldn't save the config, ",
+			path.display(),
+			" isn't valid TOML: ",
+			err.message()
+		)
+	})?;
+	change(document.as_table_mut());
+	write(&path, &document.to_string())
+}
+
+fn write(path: &Path, text: &str) -> Result<(), String> {
+	if let Some(dir) = path.parent() {
+		fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+	}
+	fs::write(path, text)
+		.map_err(|err| format!("{}{}{}{}", "Couldn't write ", path.display(), ": ", err))
+}
+
+/// Takes the cookie of earlier versions out of the config file, once it is
+/// in the credentials file.
+pub fn remove_cookie() -> Result<(), String> {
+	edit(|table| {
+		table.remove("cookie");
+	})
+}
+
+/// Writes the commented default config for `rusddit config init`.
+pub fn init() -> Result<PathBuf, String> {
+	let path = config_path();
+	if path.exists() {
+		return Err(format!(
+			"{}{}",
+			path.display(),
+			" already exists, move it away first to start over"
+		));
+	}
+	write(&path, DEFAULT_CONFIG)?;
+	Ok(path)
+}
+
+/// Moves the JSON config of earlier versions to the TOML file, the first
+/// time rusddit runs without one, and its cookie to the credentials file.
+/// The old file is kept as `config.txt.bak`, without the cookie. Returns what
+/// happened, if anything did.
+pub fn migrate() -> Option<String> {
+	let legacy = legacy_path();
+	let path = config_path();
+	if path.exists() || !legacy.exists() {
+		return None;
+	}
+	let text = fs::read_to_string(&legacy).unwrap_or_default();
+	// Earlier versions created the file empty on startup
+	if text.trim().is_empty() {
+		let _ = fs::remove_file(&legacy);
+		return None;
+	}
+	let old: LegacyConfig = match serde_json::from_str(&text) {
+		Ok(x) => x,
+		Err(err) => {
+			return Some(format!(
+				"{}{}{}{}",
+				"Couldn't move the old config to config.toml, ",
+				legacy.display(),
+				" isn't valid: ",
+				err
+			))
+		}
+	};
+	// The cookie goes to the file only the user can read before anything is
+	// written, and nowhere else
+	if let Err(err) = credentials::import(&old.cookie) {
+		return Some(format!(
+			"{}{}",
+			"Couldn't move the old config to config.toml: ", err
+		));
+	}
+	let config = UserConfig {
+		tabs: old.tabs,
+		code_theme: old.code_theme,
+		openers: old.openers,
+		theme: old.theme,
+		colors: old.colors,
+		color_mode: old.color_mode,
+		panes: old.panes,
+		mouse: old.mouse,
+		keymap: old.keymap,
+		keys: old.keys,
+		confirm_quit: old.confirm_quit,
+		clipboard: old.clipboard,
+		media: Media {
+			thumbnails: old.thumbnails,
+			..Media::default()
+		},
+		..UserConfig::new()
+	};
+	// Only settings that differ from the defaults go in, the rest stay as
+	// the comments of the default file show them
+	let defaults = to_item(&UserConfig::new());
+	let result = edit(|table| {
+		if let Item::Table(new) = to_item(&config) {
+			for (key, item) in new {
+				if defaults.get(key.as_str()).map(|x| x.to_string()) != Some(item.to_string()) {
+					merge(table, &key, item);
+				}
+			}
+		}
+	});
+	let backup = legacy.with_extension("txt.bak");
+	let mut kept: serde_json::Value = serde_json::from_str(&text).unwrap_or_default();
+	if let Some(x) = kept.as_object_mut() {
+		x.remove("cookie");
+	}
+	let result = result
+		.and_then(|_| write(&backup, &kept.to_string()))
+		.and_then(|_| fs::remove_file(&legacy).map_err(|x| x.to_string()));
+	Some(match result {
+		Ok(()) => format!(
+			"{}{}{}{}",
+			"Moved the settings of config.txt to ",
+			path.display(),
+			", the old file is kept without the cookie as ",
+			backup.display()
+		),
+		Err(err) => err,
+	})
+}
+
 impl UserConfig {
 	pub fn new() -> UserConfig {
 		UserConfig {
 			tabs: vec![],
+			default_feed: String::new(),
 			cookie: "".to_string(),
+			mouse: default_mouse(),
+			confirm_quit: false,
+			clipboard: default_clipboard(),
+			theme: default_theme(),
+			color_mode: default_color_mode(),
+			code_theme: default_code_theme(),
+			keymap: default_keymap(),
+			account: String::new(),
+			accounts: BTreeMap::new(),
+			sort: Sorts::default(),
+			filters: Filters::default(),
+			network: Network::default(),
+			media: Media::default(),
+			colors: HashMap::new(),
+			panes: PaneSizes::default(),
+			keys: HashMap::new(),
+			openers: opener::default_openers(),
+			messages: vec![],
 		}
 	}
-	pub fn readConfig(&mut self) {
-		let config_string = match std::fs::read_to_string(Path::join(
-			home::home_dir().expect("what").as_path(),
-			Path::new(".config/rusddit/config.txt"),
-		)) {
+
+	/// Reads the config file. Without one the defaults are used; when it
+	/// can't be read they are used too, with the problem in `messages`.
+	pub fn load() -> UserConfig {
+		let path = config_path();
+		let text = match fs::read_to_string(&path) {
 			Ok(x) => x,
-			Err(_x) => {
-				println!("Couldn't find cookie file. Starting an anonymous session");
-				"".to_string()
-			}
+			Err(_) => return UserConfig::new(),
 		};
-		if (config_string == "".to_string()) {
-			self.cookie = "".to_string();
-			self.tabs = vec![];
-			return;
+		match toml::from_str::<UserConfig>(&text) {
+			Ok(mut config) => {
+				config.messages = config.validate(&text);
+				config
+			}
+			Err(err) => {
+				let line = err
+					.span()
+					.map(|x| text[..x.start].matches('\n').count() + 1)
+					.unwrap_or(1);
+				UserConfig {
+					messages: vec![format!(
+						"{}{}{}{}{}{}",
+						path.display(),
+						" line ",
+						line,
+						": ",
+						err.message().trim(),
+						"\nStarting with the default settings"
+					)],
+					..UserConfig::new()
+				}
+			}
 		}
-		let config: UserConfig = serde_json::from_str(&config_string).unwrap_or(UserConfig {
-			tabs: vec![],
-			cookie: "".to_string(),
-		});
-		self.cookie = config.cookie;
-		self.tabs = config.tabs;
-		return;
-	}
-	pub fn changeConfig(&mut self, new_cookie: Option<String>, new_tabs: Option<Vec<String>>) {
-		self.cookie = new_cookie.unwrap_or(self.cookie.clone());
-		self.tabs = new_tabs.unwrap_or(self.tabs.clone());
-		match serde_json::to_string(self) {
-			Ok(x) => fs::write(
-				Path::join(
-					home::home_dir().expect("what").as_path(),
-					Path::new(".config/rusddit/config.txt"),
-				),
-				x,
-			)
-			.unwrap(),
-			Err(_x) => {
-				println!("failed to change config")
+	}
+
+	/// Settings that are the right type but not a valid value, with the line
+	/// they are on. They are set back to their defaults, so they aren't used.
+	fn validate(&mut self, text: &str) -> Vec<String> {
+		let comment_sorts: Vec<&str> = SORTS.iter().copied().filter(|x| *x != "rising").collect();
+		let code_themes = highlight::themes();
+		let code_themes: Vec<&str> = code_themes.iter().map(|x| x.as_str()).collect();
+		let checks: [(&str, &str, &str, &[&str]); 8] = [
+			("", "clipboard", &self.clipboard, &clipboard::BACKENDS),
+			("", "theme", &self.theme, &theme::THEMES),
+			("", "color_mode", &self.color_mode, &theme::COLOR_MODES),
+			("", "code_theme", &self.code_theme, &code_themes),
+			("", "keymap", &self.keymap, &keymap::PRESETS),
+			("sort", "posts", &self.sort.posts, &SORTS),
+			("sort", "time", &self.sort.time, &TIMES),
+			("sort", "comments", &self.sort.comments, &comment_sorts),
+		];
+		let mut errors = vec![];
+		let mut invalid = vec![];
+		for (section, key, value, allowed) in checks {
+			if allowed.contains(&value) || (key == "time" && value.is_empty()) {
+				continue;
+			}
+			invalid.push(key);
+			errors.push(format!(
+				"{}{}{}{}{}{}{}",
+				place(text, section, key),
+				": unknown ",
+				key,
+				" \"",
+				value,
+				"\", choose one of ",
+				allowed.join(", ")
+			));
+		}
+		if self.network.timeout == 0 {
+			errors.push(format!(
+				"{}{}",
+				place(text, "network", "timeout"),
+				": timeout has to be at least 1 second"
+			));
+			self.network.timeout = Network::default().timeout;
+		}
+		let defaults = UserConfig::new();
+		for key in invalid {
+			match key {
+				"clipboard" => self.clipboard = defaults.clipboard.clone(),
+				"theme" => self.theme = defaults.theme.clone(),
+				"color_mode" => self.color_mode = defaults.color_mode.clone(),
+				"code_theme" => self.code_theme = defaults.code_theme.clone(),
+				"keymap" => self.keymap = defaults.keymap.clone(),
+				"posts" => self.sort.posts = defaults.sort.posts.clone(),
+				"time" => self.sort.time = defaults.sort.time.clone(),
+				_ => self.sort.comments = defaults.sort.comments.clone(),
 			}
 		}
+		errors
+	}
+
+	/// Feeds `account` starts with: its tabs, or the default feed without any.
+	pub fn feeds(&self, account: &str) -> Vec<String> {
+		let tabs = match account {
+			"" => self.tabs.clone(),
+			x => self
+				.accounts
+				.get(x)
+				.map(|x| x.tabs.clone())
+				.unwrap_or_default(),
+		};
+		match tabs.is_empty() {
+			true => vec![self.default_feed.clone()],
+			false => tabs,
+		}
+	}
+
+	/// Saves the feed tabs of `account`, keeping the rest of the config file
+	/// as it is.
+	pub fn change_tabs(&mut self, account: &str, tabs: Vec<String>) -> Result<(), String> {
+		let item = to_item(&tabs);
+		if account.is_empty() {
+			self.tabs = tabs;
+			return edit(|table| merge(table, "tabs", item));
+		}
+		self.accounts.entry(account.to_string()).or_default().tabs = tabs;
+		let mut entry = Table::new();
+		entry.decor_mut().set_prefix("\n");
+		entry.insert("tabs", item);
+		let mut accounts = Table::new();
+		accounts.set_implicit(true);
+		accounts.insert(account, Item::Table(entry));
+		edit(|table| merge(table, "accounts", Item::Table(accounts)))
+	}
+
+	/// Saves new pane sizes, keeping the rest of the config file as it is.
+	pub fn change_panes(panes: &PaneSizes) -> Result<(), String> {
+		let panes = to_item(panes);
+		edit(|table| merge(table, "panes", panes))
 	}
 }
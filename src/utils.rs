@@ -1,26 +1,63 @@
-use std::path::Path;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-pub fn get_posts(
-	input: String,
-	before: bool,
-	sort_by: String,
-	mut last_post_id: &mut String,
-	reddit_cookie: String,
-) -> Vec<serde_json::Value> {
-	let slash = match input.as_str() {
-		"" => "",
-		_ => "/",
+use crate::user_config::Network;
+
+const API_URL: &str = "https://www.reddit.com";
+
+/// Reddit's request quota as of the last response.
+#[derive(Clone, Copy)]
+pub struct RateLimit {
+	pub remaining: f64,
+	reset: u64,
+	seen: Instant,
+}
+
+impl RateLimit {
+	/// Seconds until the quota is refilled.
+	pub fn resets_in(&self) -> u64 {
+		self.reset.saturating_sub(self.seen.elapsed().as_secs())
+	}
+}
+
+static RATE_LIMIT: Mutex<Option<RateLimit>> = Mutex::new(None);
+
+/// The `[network]` settings of the config, used by every client.
+static NETWORK: Mutex<Option<Network>> = Mutex::new(None);
+
+pub fn configure(network: &Network) {
+	*NETWORK.lock().unwrap() = Some(network.clone());
+}
+
+pub fn rate_limit() -> Option<RateLimit> {
+	*RATE_LIMIT.lock().unwrap()
+}
+
+/// Sends a request, remembering the quota reddit reports in its headers.
+pub fn send(request: RequestBuilder) -> reqwest::Result<Response> {
+	let response = request.send()?;
+	let header = |name: &str| {
+		response
+			.headers()
+			.get(name)
+			.and_then(|x| x.to_str().ok())
+			.and_then(|x| x.parse::<f64>().ok())
 	};
-	let mut url = format!(
-		"{}{}{}{}{}",
-		"https://reddit.com/", input, slash, sort_by, ".json?limit=100"
-	);
-	if before {
-		url = format!("{}{}{}", url, "&after=", last_post_id.clone())
+	if let (Some(remaining), Some(reset)) =
+		(header("x-ratelimit-remaining"), header("x-ratelimit-reset"))
+	{
+		*RATE_LIMIT.lock().unwrap() = Some(RateLimit {
+			remaining,
+			reset: reset as u64,
+			seen: Instant::now(),
+		});
 	}
+	Ok(response)
+}
 
-	let client_builder: reqwest::blocking::ClientBuilder;
-	if reddit_cookie != "" {
+pub fn build_client(reddit_cookie: &str) -> Client {
+	let client_builder: reqwest::blocking::ClientBuilder = if !reddit_cookie.is_empty() {
 		let cookie = &format!(
 			"{}{}{}",
 			"reddit_session=", reddit_cookie, "; Domain=reddit.com"
@@ -31,54 +68,267 @@ pub fn get_posts(
 		jar.add_cookie_str(cookie, &cookie_url);
 		let cookie_store = std::sync::Arc::new(jar);
 
-		client_builder = reqwest::blocking::Client::builder()
+		reqwest::blocking::Client::builder()
 			.cookie_store(true)
-			.cookie_provider(cookie_store);
+			.cookie_provider(cookie_store)
 	} else {
-		client_builder = reqwest::blocking::Client::builder()
+		reqwest::blocking::Client::builder()
+	};
+	let network = NETWORK.lock().unwrap().clone().unwrap_or_default();
+	let client_builder = client_builder.timeout(Duration::from_secs(network.timeout));
+	let client_builder = match network.user_agent.is_empty() {
+		true => client_builder,
+		false => client_builder.user_agent(network.user_agent),
+	};
+	client_builder.build().expect("Fuuuck")
+}
+
+/// Posts of a feed: the front page for an empty `input`, otherwise a
+/// subreddit, a user listing or `search/<query>`. `time` limits the top and
+/// controversial sorts, e.g. to `week`.
+pub fn get_posts(
+	input: String,
+	before: bool,
+	sort_by: String,
+	time: &str,
+	last_post_id: &mut String,
+	reddit_cookie: String,
+) -> Vec<serde_json::Value> {
+	let mut url = match input.as_str() {
+		"" => format!("{}{}{}", "https://reddit.com/", sort_by, ".json?limit=100"),
+		x if x.starts_with("search/") => reqwest::Url::parse_with_params(
+			"https://reddit.com/search.json?limit=100",
+			&[("q", &input["search/".len()..]), ("sort", &sort_by)],
+		)
+		.map(|x| x.to_string())
+		.unwrap_or_default(),
+		// User listings take the sort as a parameter instead of a path segment
+		x if x.starts_with("user/") => format!(
+			"{}{}{}{}",
+			"https://reddit.com/", input, ".json?limit=100&sort=", sort_by
+		),
+		_ => format!(
+			"{}{}{}{}{}",
+			"https://reddit.com/", input, "/", sort_by, ".json?limit=100"
+		),
+	};
+	if !time.is_empty() && (sort_by == "top" || sort_by == "controversial") {
+		url = format!("{}{}{}", url, "&t=", time)
+	}
+	if before {
+		url = format!("{}{}{}", url, "&after=", last_post_id.clone())
+	}
+	listing_posts(get_listing(&url, &reddit_cookie), last_post_id)
+}
+
+/// Posts the logged in user has hidden.
+pub fn get_hidden_posts(
+	username: &str,
+	before: bool,
+	last_post_id: &mut String,
+	reddit_cookie: String,
+) -> Vec<serde_json::Value> {
+	let mut url = format!(
+		"{}{}{}{}",
+		"https://reddit.com/user/", username, "/hidden", ".json?limit=100"
+	);
+	if before {
+		url = format!("{}{}{}", url, "&after=", last_post_id.clone())
 	}
-	let client = client_builder.build().expect("Fuuuck");
-	let req = client.get(&url).build().expect("fuck");
-	let client_body = client.execute(req).expect("client request failed");
+	listing_posts(get_listing(&url, &reddit_cookie), last_post_id)
+}
+
+fn get_listing(url: &str, reddit_cookie: &str) -> serde_json::Value {
+	let client = build_client(reddit_cookie);
+	let client_body = send(client.get(url)).expect("client request failed");
 	let body = match client_body.text() {
 		Ok(x) => x,
 		Err(err) => {
 			panic!("called `Result::unwrap()` on an `Err` value: {:?}", err)
 		}
 	};
-	return filter_out_text_posts(
-		match serde_json::from_str(&body) {
-			Ok(x) => x,
-			Err(x) => panic!("{:?}", x),
-		},
-		&mut last_post_id,
-	)
-	.to_owned();
+	match serde_json::from_str(&body) {
+		Ok(x) => x,
+		Err(x) => panic!("{:?}", x),
+	}
 }
 
-pub fn filter_out_text_posts(
-	v: serde_json::Value,
-	last_post_id: &mut String,
-) -> Vec<serde_json::Value> {
-	let ammount = match v["data"]["dist"].as_i64() {
-		Some(x) => x,
-		None => 0,
-	};
-	*last_post_id = v["data"]["children"][ammount as usize - 1]["data"]["name"]
-		.as_str()
-		.expect("msg")
-		.clone()
-		.to_string();
-
-	let p = match v["data"]["children"].as_array() {
-		Some(x) => x.clone(),
-		None => panic!(),
+/// The posts of a listing, remembering the last one so the next page can be
+/// requested after it.
+pub fn listing_posts(v: serde_json::Value, last_post_id: &mut String) -> Vec<serde_json::Value> {
+	let posts = v["data"]["children"]
+		.as_array()
+		.cloned()
+		.unwrap_or_default();
+	if let Some(last) = posts.last() {
+		*last_post_id = last["data"]["name"]
+			.as_str()
+			.unwrap_or_default()
+			.to_string();
+	}
+	posts
+}
+
+pub fn get_json(url: &str, reddit_cookie: &str) -> Result<serde_json::Value, String> {
+	let client = build_client(reddit_cookie);
+	let body = send(client.get(url))
+		.and_then(|x| x.text())
+		.map_err(|err| format!("{}{}", "Request failed: ", err))?;
+	serde_json::from_str(&body).map_err(|err| format!("{}{}", "Invalid response: ", err))
+}
+
+/// A post and its comments. With `comment_id` only that comment and its
+/// replies are returned, like reddit does for comment permalinks.
+pub fn get_thread(
+	post_id: &str,
+	comment_id: Option<&str>,
+	sort_by: &str,
+	reddit_cookie: &str,
+) -> Result<serde_json::Value, String> {
+	let url = match comment_id {
+		Some(comment_id) => format!(
+			"{}{}{}{}{}{}",
+			"https://reddit.com/comments/", post_id, "/_/", comment_id, ".json?sort=", sort_by
+		),
+		None => format!(
+			"{}{}{}{}",
+			"https://reddit.com/comments/", post_id, ".json?sort=", sort_by
+		),
 	};
-	let s = p
-		.iter()
+	let thread = get_json(&url, reddit_cookie)?;
+	match thread[0]["data"]["children"][0]["kind"] == "t3" {
+		true => Ok(thread),
+		false => Err("Couldn't find that post".to_string()),
+	}
+}
+
+pub fn get_bytes(url: &str, reddit_cookie: &str) -> Result<Vec<u8>, String> {
+	let client = build_client(reddit_cookie);
+	send(client.get(url))
+		.and_then(|x| x.bytes())
+		.map(|x| x.to_vec())
+		.map_err(|err| format!("{}{}", "Request failed: ", err))
+}
+
+/// Sends an authenticated POST to one of reddit's `/api/` endpoints. Cookie
+/// sessions need the account's modhash on every state changing request.
+pub fn post_api(
+	endpoint: &str,
+	params: &[(&str, &str)],
+	modhash: &str,
+	reddit_cookie: &str,
+) -> Result<serde_json::Value, String> {
+	if reddit_cookie.is_empty() {
+		return Err("You need to be logged in to do that".to_string());
+	}
+	let mut form = params.to_vec();
+	form.push(("api_type", "json"));
+	let client = build_client(reddit_cookie);
+	let response = send(
+		client
+			.post(format!("{}{}", API_URL, endpoint))
+			.header("X-Modhash", modhash)
+			.form(&form),
+	)
+	.map_err(|err| format!("{}{}", "Request failed: ", err))?;
+	let status = response.status();
+	let body = response.text().unwrap_or_default();
+	if !status.is_success() {
+		return Err(format!("{}{}{}", endpoint, " failed with ", status));
+	}
+	let v: serde_json::Value = serde_json::from_str(&body).unwrap_or(serde_json::Value::Null);
+	if let Some(error) = v["json"]["errors"].as_array().and_then(|x| x.first()) {
+		return Err(format!("{}{}{}", endpoint, " failed: ", error));
+	}
+	Ok(v)
+}
+
+/// The `data` object of `/api/me.json`, or `None` for anonymous sessions.
+pub fn get_me(reddit_cookie: &str) -> Option<serde_json::Value> {
+	if reddit_cookie.is_empty() {
+		return None;
+	}
+	let me = get_json(&format!("{}{}", API_URL, "/api/me.json"), reddit_cookie).ok()?;
+	me["data"]["name"].as_str()?;
+	Some(me["data"].clone())
+}
+
+/// Report reasons for a subreddit: its own rules followed by reddit's site wide rules.
+pub fn get_report_reasons(subreddit: &str, reddit_cookie: &str) -> Result<Vec<String>, String> {
+	let rules = get_json(
+		&format!("{}{}{}{}", API_URL, "/r/", subreddit, "/about/rules.json"),
+		reddit_cookie,
+	)?;
+	let mut reasons: Vec<String> = rules["rules"]
+		.as_array()
 		.cloned()
-		.filter(|x| x.clone()["data"]["selftext"] != "");
-	//.filter(|x| true);
-	let m: Vec<serde_json::Value> = s.collect();
-	return m;
+		.unwrap_or_default()
+		.iter()
+		.filter_map(|x| x["short_name"].as_str().map(|x| x.to_string()))
+		.collect();
+	for rule in rules["site_rules"].as_array().cloned().unwrap_or_default() {
+		if let Some(x) = rule.as_str() {
+			reasons.push(x.to_string());
+		}
+	}
+	Ok(reasons)
+}
+
+fn get_children(url: &str, reddit_cookie: &str) -> Result<Vec<serde_json::Value>, String> {
+	let v = get_json(url, reddit_cookie)?;
+	match v["data"]["children"].as_array() {
+		Some(x) => Ok(x.clone()),
+		None => Err(format!("{}{}", "Unexpected response from ", url)),
+	}
+}
+
+/// Names of the subreddits the logged in user moderates.
+pub fn get_moderated_subreddits(reddit_cookie: &str) -> Result<Vec<String>, String> {
+	let subreddits = get_children(
+		&format!("{}{}", API_URL, "/subreddits/mine/moderator.json?limit=100"),
+		reddit_cookie,
+	)?;
+	Ok(subreddits
+		.iter()
+		.filter_map(|x| x["data"]["display_name"].as_str().map(|x| x.to_string()))
+		.collect())
+}
+
+/// One of the moderation listings (modqueue, reports, spam, unmoderated) of a subreddit.
+pub fn get_mod_listing(
+	subreddit: &str,
+	listing: &str,
+	reddit_cookie: &str,
+) -> Result<Vec<serde_json::Value>, String> {
+	get_children(
+		&format!(
+			"{}{}{}{}{}{}",
+			API_URL, "/r/", subreddit, "/about/", listing, ".json?limit=100"
+		),
+		reddit_cookie,
+	)
+}
+
+/// The removal reasons a subreddit has set up, as (id, title) pairs in the
+/// order the moderators arranged them.
+pub fn get_removal_reasons(
+	subreddit: &str,
+	reddit_cookie: &str,
+) -> Result<Vec<(String, String)>, String> {
+	let v = get_json(
+		&format!(
+			"{}{}{}{}",
+			API_URL, "/api/v1/", subreddit, "/removal_reasons"
+		),
+		reddit_cookie,
+	)?;
+	let order = v["order"].as_array().cloned().unwrap_or_default();
+	Ok(order
+		.iter()
+		.filter_map(|x| x.as_str())
+		.map(|id| {
+			let title = v["data"][id]["title"].as_str().unwrap_or(id);
+			(id.to_string(), title.to_string())
+		})
+		.collect())
 }
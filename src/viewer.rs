@@ -0,0 +1,194 @@
+use tui::{
+	backend::Backend,
+	layout::{Alignment, Constraint, Direction, Layout},
+	text::Spans,
+	widgets::{Block, Borders, Clear, Paragraph, Wrap},
+	Frame,
+};
+
+use crate::markdown::decode_entities;
+use crate::media::{self, Images};
+use crate::theme::Theme;
+
+const ZOOM_LEVELS: [u32; 5] = [100, 150, 200, 300, 400];
+
+/// The full screen image viewer. It shows the images of one post at a time and
+/// can move on to the other image posts of the feed.
+pub struct Viewer {
+	/// Index of the post in the feed.
+	pub post: usize,
+	pub images: Vec<(String, Option<String>)>,
+	pub current: usize,
+	zoom: usize,
+	pan: (i32, i32),
+}
+
+/// Everything the viewer can show for a post: all gallery items, otherwise the
+/// post's image or preview.
+fn post_images(post: &serde_json::Value) -> Vec<(String, Option<String>)> {
+	let gallery = media::gallery_images(post);
+	if !gallery.is_empty() {
+		return gallery;
+	}
+	media::post_image(post)
+		.map(|x| vec![(x, None)])
+		.unwrap_or_default()
+}
+
+impl Viewer {
+	pub fn open(v: &[serde_json::Value], post: usize) -> Option<Viewer> {
+		let images = post_images(&v.get(post)?["data"]);
+		if images.is_empty() {
+			return None;
+		}
+		Some(Viewer {
+			post,
+			images,
+			current: 0,
+			zoom: 0,
+			pan: (0, 0),
+		})
+	}
+
+	fn show_post(&mut self, v: &[serde_json::Value], post: usize, last_image: bool) {
+		self.post = post;
+		self.images = post_images(&v[post]["data"]);
+		self.current = match last_image {
+			true => self.images.len().saturating_sub(1),
+			false => 0,
+		};
+		self.reset_zoom();
+	}
+
+	/// Moves to the next image post of the feed, returns false if there is none.
+	pub fn next_post(&mut self, v: &[serde_json::Value]) -> bool {
+		match (self.post + 1..v.len()).find(|x| media::is_image_post(&v[*x]["data"])) {
+			Some(post) => {
+				self.show_post(v, post, false);
+				true
+			}
+			None => false,
+		}
+	}
+
+	pub fn previous_post(&mut self, v: &[serde_json::Value], last_image: bool) -> bool {
+		match (0..self.post)
+			.rev()
+			.find(|x| media::is_image_post(&v[*x]["data"]))
+		{
+			Some(post) => {
+				self.show_post(v, post, last_image);
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// Next gallery item, carrying on with the next image post after the last one.
+	pub fn next_image(&mut self, v: &[serde_json::Value]) {
+		if self.current + 1 < self.images.len() {
+			self.current += 1;
+			self.reset_zoom();
+		} else {
+			self.next_post(v);
+		}
+	}
+
+	pub fn previous_image(&mut self, v: &[serde_json::Value]) {
+		if self.current > 0 {
+			self.current -= 1;
+			self.reset_zoom();
+		} else {
+			self.previous_post(v, true);
+		}
+	}
+
+	pub fn zoom_in(&mut self) {
+		self.zoom = (self.zoom + 1).min(ZOOM_LEVELS.len() - 1);
+		self.clamp_pan();
+	}
+
+	pub fn zoom_out(&mut self) {
+		self.zoom = self.zoom.saturating_sub(1);
+		self.clamp_pan();
+	}
+
+	pub fn reset_zoom(&mut self) {
+		self.zoom = 0;
+		self.pan = (0, 0);
+	}
+
+	pub fn pan(&mut self, x: i32, y: i32) {
+		self.pan = (self.pan.0 + x, self.pan.1 + y);
+		self.clamp_pan();
+	}
+
+	/// Keeps the zoomed in window inside the image: each pan step moves it by
+	/// a quarter of its size.
+	fn clamp_pan(&mut self) {
+		let steps = (2 * (ZOOM_LEVELS[self.zoom] - 100) / 100) as i32;
+		self.pan = (
+			self.pan.0.clamp(-steps, steps),
+			self.pan.1.clamp(-steps, steps),
+		);
+	}
+}
+
+pub fn draw_viewer<B>(
+	f: &mut Frame<B>,
+	viewer: &Viewer,
+	images: &mut Images,
+	v: &[serde_json::Value],
+	theme: &Theme,
+) where
+	B: Backend,
+{
+	let size = f.size();
+	let post = &v[viewer.post]["data"];
+	let (url, caption) = &viewer.images[viewer.current];
+	let title = format!(
+		"{}{}{}{}{}{}{}",
+		" ",
+		viewer.current + 1,
+		"/",
+		viewer.images.len(),
+		" | ",
+		post["subreddit_name_prefixed"].as_str().unwrap_or_default(),
+		" "
+	);
+	let block = Block::default()
+		.title(title)
+		.borders(Borders::ALL)
+		.border_style(theme.border(true));
+	let inner = block.inner(size);
+	f.render_widget(Clear, size);
+	f.render_widget(block, size);
+	let chunks = Layout::default()
+		.direction(Direction::Vertical)
+		.constraints([Constraint::Min(0), Constraint::Length(2)].as_ref())
+		.split(inner);
+	images.hide();
+	if !images.enabled {
+		let message = Paragraph::new("Images are turned off, press i to show them")
+			.alignment(Alignment::Center);
+		f.render_widget(message, chunks[0]);
+	}
+	images.request_zoomed(url.clone(), chunks[0], ZOOM_LEVELS[viewer.zoom], viewer.pan);
+
+	let title = decode_entities(post["title"].as_str().unwrap_or_default());
+	let mut lines = vec![Spans::from(match caption {
+		Some(caption) => format!("{}{}{}", title, " | ", caption),
+		None => title,
+	})];
+	lines.push(Spans::from(format!(
+		"{}{}{}",
+		"zoom ",
+		ZOOM_LEVELS[viewer.zoom],
+		"% | ←/→ image  [/] post  +/- zoom  hjkl pan  0 reset  i images  esc close"
+	)));
+	let footer = Paragraph::new(lines)
+		.style(theme.base())
+		.alignment(Alignment::Center)
+		.wrap(Wrap { trim: true });
+	f.render_widget(footer, chunks[1]);
+}